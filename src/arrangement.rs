@@ -0,0 +1,270 @@
+use serde::Serialize;
+
+use crate::{grid::cos_sin_degrees, Grid, Point, Rect, Region, Segment};
+
+/// One of the faces into which the lines of the grids divide the viewable area. Where the clip
+/// isn't a rectangle, a face crossing its edge is cut into pieces, each with the indices of the
+/// whole face.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct Face {
+    /// Vertices of the face, which is always convex
+    pub points: Vec<Point>,
+    /// For each grid of parallel lines, in the order of the collection, the index of the line on
+    /// the lower side of the face: the face lies between lines `i` and `i + 1` of the grid. A
+    /// grid with a region doesn't divide the faces outside it, which take the index of the line
+    /// below their center.
+    pub indices: Vec<i64>,
+}
+
+/// Returns the faces into which the lines of `grids` divide `area`, with the lines of a grid with
+/// a region only dividing the area inside it.
+pub(crate) fn faces(grids: &[&Grid], area: Rect) -> Vec<Face> {
+    let mut faces = vec![vec![
+        Point::new(area.min_x, area.min_y),
+        Point::new(area.max_x, area.min_y),
+        Point::new(area.max_x, area.max_y),
+        Point::new(area.min_x, area.max_y),
+    ]];
+    let positions = grids
+        .iter()
+        .map(|grid| {
+            let (theta, offset) = grid.line_position(0);
+            let step = grid.line_position(1).1 - offset;
+            let (cos, sin) = cos_sin_degrees(theta);
+            // position of a point relative to the lines, in steps from line 0
+            move |p: Point| (p.dot(Point::new(cos, sin)) - offset) / step
+        })
+        .collect::<Vec<_>>();
+    for (grid, position) in grids.iter().zip(&positions) {
+        let region = grid.region.as_ref().map(Region::polygonal);
+        let edges = region.iter().flat_map(Region::edges).collect::<Vec<_>>();
+        let mut split = Vec::with_capacity(faces.len());
+        let pieces = faces.iter().flat_map(|face| cut_along(face, &edges));
+        for face in pieces {
+            if region
+                .as_ref()
+                .is_some_and(|region| !region.contains(centroid(&face)))
+            {
+                split.push(face);
+                continue;
+            }
+            let values = face.iter().map(|&p| position(p)).collect::<Vec<_>>();
+            let min = values.iter().copied().fold(f64::INFINITY, f64::min);
+            let max = values.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+            // slice off the part below each line crossing the face in turn
+            let mut rest = face;
+            let mut line = (min + TOLERANCE).ceil();
+            while line < max - TOLERANCE {
+                let (below, above) = cut(&rest, |p| position(p) - line);
+                split.extend(below);
+                rest = above.unwrap_or_default();
+                if rest.is_empty() {
+                    break;
+                }
+                line += 1.0;
+            }
+            if !rest.is_empty() {
+                split.push(rest);
+            }
+        }
+        faces = split;
+    }
+    faces
+        .into_iter()
+        .map(|points| {
+            let centroid = centroid(&points);
+            let indices = positions
+                .iter()
+                .map(|position| position(centroid).floor() as i64)
+                .collect();
+            Face { points, indices }
+        })
+        .collect()
+}
+
+/// Returns the parts of `faces` inside `clip`. Faces crossing its edge are cut along the lines
+/// through the edges crossing them, so the pieces stay convex, and keep the indices of the face
+/// they were cut from. Circles and ellipses are approximated by polygons.
+pub(crate) fn clip_faces(faces: Vec<Face>, clip: &Region) -> Vec<Face> {
+    let clip = clip.polygonal();
+    let edges = clip.edges().collect::<Vec<_>>();
+    let mut clipped = Vec::with_capacity(faces.len());
+    for face in faces {
+        clipped.extend(
+            cut_along(&face.points, &edges)
+                .into_iter()
+                .filter(|piece| clip.contains(centroid(piece)))
+                .map(|points| Face {
+                    points,
+                    indices: face.indices.clone(),
+                }),
+        );
+    }
+    clipped
+}
+
+/// Cuts a convex polygon along the lines through those of `edges` which cross it. Each piece is
+/// convex, and lies entirely on one side of every edge.
+fn cut_along(polygon: &[Point], edges: &[Segment]) -> Vec<Vec<Point>> {
+    let mut pieces = vec![polygon.to_vec()];
+    for edge in edges.iter().filter(|&&edge| crosses(polygon, edge)) {
+        let direction = edge.end - edge.start;
+        let length = direction.length();
+        let side = |p: Point| (p - edge.start).cross(direction) / length;
+        pieces = pieces
+            .iter()
+            .flat_map(|piece| {
+                let (below, above) = cut(piece, side);
+                below.into_iter().chain(above)
+            })
+            .collect();
+    }
+    pieces
+}
+
+/// Whether `segment` passes through the inside of the convex polygon.
+fn crosses(polygon: &[Point], segment: Segment) -> bool {
+    // look for a line separating them: either the segment's own, or one of the polygon's edges
+    let direction = segment.end - segment.start;
+    let sides = polygon
+        .iter()
+        .map(|&p| (p - segment.start).cross(direction))
+        .collect::<Vec<_>>();
+    let limit = TOLERANCE * direction.length();
+    if sides.iter().all(|&side| side <= limit) || sides.iter().all(|&side| side >= -limit) {
+        return false;
+    }
+    let orientation = polygon_area(polygon).signum();
+    !polygon.iter().enumerate().any(|(i, &a)| {
+        let edge = polygon[(i + 1) % polygon.len()] - a;
+        // positive outside the edge
+        let outside = |p: Point| -orientation * edge.cross(p - a);
+        let limit = TOLERANCE * edge.length();
+        outside(segment.start) >= -limit && outside(segment.end) >= -limit
+    })
+}
+
+/// Twice the signed area of a polygon, positive if its vertices run counterclockwise when y points
+/// up.
+fn polygon_area(polygon: &[Point]) -> f64 {
+    (0..polygon.len())
+        .map(|i| polygon[i].cross(polygon[(i + 1) % polygon.len()]))
+        .sum()
+}
+
+fn centroid(points: &[Point]) -> Point {
+    points.iter().fold(Point::default(), |sum, &p| sum + p) * (1.0 / points.len() as f64)
+}
+
+/// Distance from a line, in steps of its grid, within which a vertex is considered to lie on it.
+const TOLERANCE: f64 = 1e-9;
+
+/// Cuts a convex polygon in two where `side` changes sign, returning the parts where it is
+/// negative and positive, or `None` for a part which would be empty.
+fn cut(polygon: &[Point], side: impl Fn(Point) -> f64) -> (Option<Vec<Point>>, Option<Vec<Point>>) {
+    let mut below = Vec::new();
+    let mut above = Vec::new();
+    for (i, &current) in polygon.iter().enumerate() {
+        let next = polygon[(i + 1) % polygon.len()];
+        let (d0, d1) = (side(current), side(next));
+        if d0 <= TOLERANCE {
+            below.push(current);
+        }
+        if d0 >= -TOLERANCE {
+            above.push(current);
+        }
+        if (d0 < -TOLERANCE && d1 > TOLERANCE) || (d0 > TOLERANCE && d1 < -TOLERANCE) {
+            let crossing = current + (next - current) * (d0 / (d0 - d1));
+            below.push(crossing);
+            above.push(crossing);
+        }
+    }
+    let part = |points: Vec<Point>| Some(points).filter(|points| points.len() >= 3);
+    (part(below), part(above))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Shape;
+
+    fn total_area(faces: &[Face]) -> f64 {
+        faces
+            .iter()
+            .map(|face| polygon_area(&face.points).abs() / 2.0)
+            .sum()
+    }
+
+    #[test]
+    fn crossing_grids_make_cells() {
+        let grids = [
+            Grid::new(10.0),
+            Grid::new(20.0).with_theta(90.0).with_center(0.0, 5.0),
+        ];
+        let faces = faces(&[&grids[0], &grids[1]], Rect::new(0.0, 100.0, 0.0, 100.0));
+        // the rows are cut at 5, 25, ..., 85, leaving shorter rows at the top and bottom
+        assert_eq!(faces.len(), 10 * 6);
+        assert!((total_area(&faces) - 100.0 * 100.0).abs() < 1e-6);
+        assert!(faces.iter().all(|face| face.points.len() == 4));
+        let face_at = |x, y| {
+            faces
+                .iter()
+                .find(|face| centroid(&face.points).distance(Point::new(x, y)) < 1e-6)
+                .unwrap()
+        };
+        assert_eq!(face_at(35.0, 55.0).indices, vec![3, 2]);
+        // the shorter rows belong to the lines below them, inside the area or not
+        assert_eq!(face_at(5.0, 2.5).indices, vec![0, -1]);
+        assert_eq!(face_at(95.0, 92.5).indices, vec![9, 4]);
+    }
+
+    #[test]
+    fn clipped_faces_cover_the_clip() {
+        let grids = [Grid::new(10.0), Grid::new(10.0).with_theta(90.0)];
+        let faces = faces(&[&grids[0], &grids[1]], Rect::new(0.0, 100.0, 0.0, 100.0));
+        let triangle = vec![
+            Point::new(5.0, 5.0),
+            Point::new(95.0, 5.0),
+            Point::new(5.0, 95.0),
+        ];
+        let clip = Region::new(Shape::Polygon(
+            triangle.into_iter().map(Point::into).collect(),
+        ))
+        .with_hole(Shape::Rect(Rect::new(20.0, 30.0, 20.0, 30.0).into()));
+        let clipped = clip_faces(faces, &clip);
+        assert!((total_area(&clipped) - (90.0 * 90.0 / 2.0 - 100.0)).abs() < 1e-6);
+        assert!(clipped
+            .iter()
+            .all(|face| polygon_area(&face.points).abs() > 0.0));
+        // a face cut by the hypotenuse keeps the indices of the whole face
+        let corner = clipped
+            .iter()
+            .find(|face| {
+                face.points.contains(&Point::new(40.0, 60.0))
+                    && face.points.contains(&Point::new(50.0, 50.0))
+            })
+            .unwrap();
+        assert_eq!(corner.points.len(), 3);
+        assert_eq!(corner.indices, vec![4, 5]);
+    }
+
+    #[test]
+    fn regions_limit_the_division() {
+        let area = Rect::new(0.0, 100.0, 0.0, 100.0);
+        let vertical = Grid::new(10.0);
+        let horizontal = Grid::new(10.0)
+            .with_theta(90.0)
+            .with_region(Rect::new(0.0, 45.0, 0.0, 100.0).into());
+        let faces = faces(&[&vertical, &horizontal], area);
+        // the column from 40 to 50 is cut in two by the edge of the region, and only the left part
+        // is divided into rows
+        assert_eq!(faces.len(), 5 * 10 + 1 + 5);
+        assert!((total_area(&faces) - 100.0 * 100.0).abs() < 1e-6);
+        let right = faces
+            .iter()
+            .find(|face| face.points.contains(&Point::new(100.0, 0.0)))
+            .unwrap();
+        assert_eq!(right.points.len(), 4);
+        assert_eq!(right.indices, vec![9, 5]);
+    }
+}
@@ -0,0 +1,70 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    error::{non_negative, GridError},
+    units::{Conversion, Length, ResolveUnits},
+};
+
+/// Settings for drawing lines shared by several grids only once.
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(default, rename_all = "kebab-case", deny_unknown_fields)]
+pub struct Deduplicate {
+    /// Maximum distance between two parallel lines for them to be considered the same
+    pub tolerance: Length,
+    /// Maximum difference in angle between two lines for them to be considered parallel, in
+    /// degrees
+    pub angle_tolerance: f64,
+    /// Which grid draws a shared line
+    pub keep: Precedence,
+}
+
+impl Default for Deduplicate {
+    fn default() -> Self {
+        Deduplicate {
+            tolerance: Length::new(1e-6),
+            angle_tolerance: 1e-6,
+            keep: Precedence::default(),
+        }
+    }
+}
+
+impl Deduplicate {
+    pub(crate) fn validate(&self, field: &str) -> Result<(), GridError> {
+        non_negative(self.tolerance.get())
+            .map_err(|kind| GridError::new(format!("{}.tolerance", field), kind))?;
+        non_negative(self.angle_tolerance)
+            .map_err(|kind| GridError::new(format!("{}.angle-tolerance", field), kind))?;
+        Ok(())
+    }
+}
+
+impl ResolveUnits for Deduplicate {
+    fn resolve_units(&mut self, units: &mut Conversion) {
+        self.tolerance.resolve_units(units);
+    }
+}
+
+/// Rule for choosing which of several grids sharing a line draws it, and so determines its style.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Precedence {
+    /// The grid listed last, which would be drawn on top
+    #[default]
+    Later,
+    /// The grid listed first
+    Earlier,
+    /// The grid with the widest stroke, or the later one if several are equally wide
+    Heavier,
+}
+
+impl Precedence {
+    /// Whether grid number `a`, with stroke width `a_width`, takes precedence over grid number
+    /// `b`, with stroke width `b_width`.
+    pub fn prefers(self, (a, a_width): (usize, f64), (b, b_width): (usize, f64)) -> bool {
+        match self {
+            Precedence::Later => a > b,
+            Precedence::Earlier => a < b,
+            Precedence::Heavier => a_width > b_width || (a_width == b_width && a > b),
+        }
+    }
+}
@@ -0,0 +1,418 @@
+use std::fmt;
+
+use serde::de::{self, DeserializeSeed, IgnoredAny, Visitor};
+
+use crate::Color;
+
+/// An invalid value in a grid configuration.
+#[derive(Clone, Debug, PartialEq)]
+pub struct GridError {
+    /// Index of the offending grid, or `None` if the error is in the collection itself
+    pub grid: Option<usize>,
+    /// Path of the offending field within the grid or collection, as written in the YAML file
+    pub field: String,
+    /// What is wrong with the field
+    pub kind: ErrorKind,
+    /// Position of the offending value in the YAML file, if known
+    pub location: Option<Location>,
+}
+
+impl GridError {
+    pub fn new(field: impl Into<String>, kind: ErrorKind) -> Self {
+        GridError {
+            grid: None,
+            field: field.into(),
+            kind,
+            location: None,
+        }
+    }
+
+    /// Attributes the error to the grid at index `grid`.
+    pub fn in_grid(mut self, grid: usize) -> Self {
+        self.grid = Some(grid);
+        self
+    }
+
+    /// Fills in the location of the error by looking up the offending field in the YAML source the
+    /// configuration was read from. If the field is missing from the source, the location of the
+    /// closest enclosing mapping is used instead.
+    pub fn locate(mut self, source: &str) -> Self {
+        let mut path = Vec::new();
+        if let Some(grid) = self.grid {
+            path.push(PathSegment::Key("grids"));
+            path.push(PathSegment::Index(grid));
+        }
+        path.extend(self.field.split('.').map(|segment| match segment.parse() {
+            Ok(index) => PathSegment::Index(index),
+            Err(_) => PathSegment::Key(segment),
+        }));
+        let deserializer = serde_yaml::Deserializer::from_str(source);
+        if let Err(err) = Locate(&path).deserialize(deserializer) {
+            if err.to_string().contains(FOUND) {
+                self.location = err.location().map(|loc| Location {
+                    line: loc.line(),
+                    column: loc.column(),
+                });
+            }
+        }
+        self
+    }
+}
+
+impl fmt::Display for GridError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if let Some(grid) = self.grid {
+            write!(f, "grids[{}].", grid)?;
+        }
+        write!(f, "{}: {}", self.field, self.kind)?;
+        if let Some(location) = self.location {
+            write!(f, " at line {} column {}", location.line, location.column)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for GridError {}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum ErrorKind {
+    /// The value is NaN or infinite
+    NotFinite(f64),
+    /// The value must be greater than zero
+    NotPositive(f64),
+    /// The value must not be less than zero
+    Negative(f64),
+    /// The minimum of a rectangle along the given axis is not less than the maximum
+    Inverted { axis: char, min: f64, max: f64 },
+    /// The clip rectangle is not contained within the bounds
+    ClipOutsideBounds,
+    /// Neither bounds nor a page were given
+    MissingBounds,
+    /// The margins of a page leave no room inside them
+    MarginsTooWide,
+    /// The value is not a recognized color
+    InvalidColor(String),
+    /// The value is less than the smallest allowed
+    TooSmall { min: f64, value: f64 },
+    /// There is no grid with the given index
+    NoSuchGrid(usize),
+    /// The grid with the given index is not made of parallel lines
+    NotLines(usize),
+    /// The lattice vectors of a tiling are parallel, or one of them is zero
+    Parallel,
+    /// A list needs one value for each grid of parallel lines
+    WrongLength { expected: usize, value: usize },
+}
+
+impl fmt::Display for ErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ErrorKind::NotFinite(value) => write!(f, "expected a finite number, got {}", value),
+            ErrorKind::NotPositive(value) => write!(f, "expected a positive number, got {}", value),
+            ErrorKind::Negative(value) => {
+                write!(f, "expected a non-negative number, got {}", value)
+            }
+            ErrorKind::Inverted { axis, min, max } => write!(
+                f,
+                "min-{axis} ({min}) must be less than max-{axis} ({max})",
+                axis = axis,
+                min = min,
+                max = max,
+            ),
+            ErrorKind::ClipOutsideBounds => write!(f, "clip rectangle extends outside of bounds"),
+            ErrorKind::MissingBounds => write!(f, "expected either `bounds` or a `page`"),
+            ErrorKind::MarginsTooWide => write!(f, "margins leave no room on the page"),
+            ErrorKind::InvalidColor(color) => write!(f, "unrecognized color {:?}", color),
+            ErrorKind::TooSmall { min, value } => {
+                write!(f, "expected at least {}, got {}", min, value)
+            }
+            ErrorKind::NoSuchGrid(grid) => write!(f, "there is no grid with index {}", grid),
+            ErrorKind::WrongLength { expected, value } => write!(
+                f,
+                "expected {} values, one for each grid of parallel lines, got {}",
+                expected, value
+            ),
+            ErrorKind::Parallel => write!(f, "lattice vectors must not be parallel or zero"),
+            ErrorKind::NotLines(grid) => {
+                write!(f, "grid {} is not made of parallel lines", grid)
+            }
+        }
+    }
+}
+
+/// An error reading a grid configuration from YAML.
+#[derive(Debug)]
+pub enum LoadError {
+    /// The YAML is malformed or doesn't match the schema
+    Yaml(serde_yaml::Error),
+    /// The configuration contains an invalid value
+    Invalid(GridError),
+}
+
+impl fmt::Display for LoadError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LoadError::Yaml(err) => err.fmt(f),
+            LoadError::Invalid(err) => err.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for LoadError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            LoadError::Yaml(err) => err.source(),
+            LoadError::Invalid(err) => err.source(),
+        }
+    }
+}
+
+impl From<serde_yaml::Error> for LoadError {
+    fn from(err: serde_yaml::Error) -> Self {
+        LoadError::Yaml(err)
+    }
+}
+
+impl From<GridError> for LoadError {
+    fn from(err: GridError) -> Self {
+        LoadError::Invalid(err)
+    }
+}
+
+/// A line and column in a YAML file, both starting from 1.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Location {
+    pub line: usize,
+    pub column: usize,
+}
+
+/// Checks that `value` is finite.
+pub fn finite(value: f64) -> Result<f64, ErrorKind> {
+    if value.is_finite() {
+        Ok(value)
+    } else {
+        Err(ErrorKind::NotFinite(value))
+    }
+}
+
+/// Checks that `value` is finite and greater than zero.
+pub fn positive(value: f64) -> Result<f64, ErrorKind> {
+    if finite(value)? > 0.0 {
+        Ok(value)
+    } else {
+        Err(ErrorKind::NotPositive(value))
+    }
+}
+
+/// Checks that `value` is finite and not less than zero.
+pub fn non_negative(value: f64) -> Result<f64, ErrorKind> {
+    if finite(value)? >= 0.0 {
+        Ok(value)
+    } else {
+        Err(ErrorKind::Negative(value))
+    }
+}
+
+/// Checks that `value` is a color which can be parsed by `Color::parse`.
+pub fn color(value: &str) -> Result<Color, ErrorKind> {
+    Color::parse(value).ok_or_else(|| ErrorKind::InvalidColor(value.into()))
+}
+
+/// Message of the error raised by `Locate` once it reaches its target.
+const FOUND: &str = "reached the located value";
+
+enum PathSegment<'a> {
+    Key(&'a str),
+    Index(usize),
+}
+
+/// Walks a YAML document along a path, and fails as soon as it reaches the end of the path (or a
+/// mapping which doesn't contain the next key), so that the deserializer reports the position of
+/// the failure.
+struct Locate<'a, 'b>(&'a [PathSegment<'b>]);
+
+impl Locate<'_, '_> {
+    fn found<T, E: de::Error>(&self) -> Result<T, E> {
+        Err(E::custom(FOUND))
+    }
+
+    fn scalar<E: de::Error>(&self) -> Result<(), E> {
+        if self.0.is_empty() {
+            self.found()
+        } else {
+            Ok(())
+        }
+    }
+}
+
+impl<'de> DeserializeSeed<'de> for Locate<'_, '_> {
+    type Value = ();
+
+    fn deserialize<D: de::Deserializer<'de>>(self, deserializer: D) -> Result<(), D::Error> {
+        deserializer.deserialize_any(self)
+    }
+}
+
+impl<'de> Visitor<'de> for Locate<'_, '_> {
+    type Value = ();
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("any value")
+    }
+
+    fn visit_map<A: de::MapAccess<'de>>(self, mut map: A) -> Result<(), A::Error> {
+        let (key, rest) = match self.0 {
+            [PathSegment::Key(key), rest @ ..] => (key, rest),
+            _ => return self.found(),
+        };
+        while let Some(k) = map.next_key::<String>()? {
+            if k == *key {
+                return map.next_value_seed(Locate(rest));
+            }
+            map.next_value::<IgnoredAny>()?;
+        }
+        self.found()
+    }
+
+    fn visit_seq<A: de::SeqAccess<'de>>(self, mut seq: A) -> Result<(), A::Error> {
+        let (index, rest) = match self.0 {
+            [PathSegment::Index(index), rest @ ..] => (*index, rest),
+            _ => return self.found(),
+        };
+        for _ in 0..index {
+            if seq.next_element::<IgnoredAny>()?.is_none() {
+                return self.found();
+            }
+        }
+        match seq.next_element_seed(Locate(rest))? {
+            Some(()) => Ok(()),
+            None => self.found(),
+        }
+    }
+
+    fn visit_bool<E: de::Error>(self, _: bool) -> Result<(), E> {
+        self.scalar()
+    }
+
+    fn visit_i64<E: de::Error>(self, _: i64) -> Result<(), E> {
+        self.scalar()
+    }
+
+    fn visit_u64<E: de::Error>(self, _: u64) -> Result<(), E> {
+        self.scalar()
+    }
+
+    fn visit_f64<E: de::Error>(self, _: f64) -> Result<(), E> {
+        self.scalar()
+    }
+
+    fn visit_str<E: de::Error>(self, _: &str) -> Result<(), E> {
+        self.scalar()
+    }
+
+    fn visit_unit<E: de::Error>(self) -> Result<(), E> {
+        self.scalar()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::GridCollection;
+
+    /// Loads `source`, which must be well-formed but invalid, and returns the error.
+    fn invalid(source: &str) -> GridError {
+        match GridCollection::from_yaml(source) {
+            Err(LoadError::Invalid(err)) => err,
+            Err(err) => panic!("malformed configuration: {}", err),
+            Ok(_) => panic!("configuration is valid"),
+        }
+    }
+
+    fn location(err: &GridError) -> Option<(usize, usize)> {
+        err.location.map(|loc| (loc.line, loc.column))
+    }
+
+    #[test]
+    fn locates_fields() {
+        let err = invalid(
+            "\
+bounds: {min-x: 0, max-x: 100, min-y: 0, max-y: 100}
+grids:
+  - step: 10
+  - type: lines
+    step: -5
+",
+        );
+        assert_eq!((err.grid, err.field.as_str()), (Some(1), "step"));
+        assert_eq!(err.kind, ErrorKind::NotPositive(-5.0));
+        assert_eq!(location(&err), Some((5, 11)));
+
+        let err = invalid(
+            "\
+bounds: {min-x: 0, max-x: 100, min-y: 0, max-y: 100}
+grids:
+  - step: 10
+    major:
+      - every: 5
+      - every: 0
+",
+        );
+        assert_eq!((err.grid, err.field.as_str()), (Some(0), "major.1.every"));
+        assert_eq!(location(&err), Some((6, 16)));
+
+        let err = invalid(
+            "\
+bounds: {min-x: 0, max-x: 100, min-y: 0, max-y: .nan}
+grids:
+  - step: 10
+",
+        );
+        assert_eq!((err.grid, err.field.as_str()), (None, "bounds.max-y"));
+        assert!(matches!(err.kind, ErrorKind::NotFinite(value) if value.is_nan()));
+        assert_eq!(location(&err), Some((1, 49)));
+    }
+
+    #[test]
+    fn falls_back_to_the_enclosing_mapping() {
+        // the inverted rectangle as a whole is at fault
+        let err = invalid(
+            "\
+grids:
+  - step: 10
+bounds:
+  min-x: 100
+  max-x: 0
+  min-y: 0
+  max-y: 100
+",
+        );
+        assert_eq!(err.field, "bounds");
+        assert_eq!(location(&err).map(|(line, _)| line), Some(4));
+        // nothing to point at but the collection
+        let err = invalid("grids:\n  - step: 10\n");
+        assert_eq!(err.kind, ErrorKind::MissingBounds);
+        assert_eq!(location(&err).map(|(line, _)| line), Some(1));
+    }
+
+    #[test]
+    fn displays_the_location() {
+        let source = "\
+bounds: {min-x: 0, max-x: 100, min-y: 0, max-y: 100}
+grids: [{step: 10, stroke-width: -1}]
+";
+        let err = invalid(source);
+        assert_eq!(
+            err.to_string(),
+            "grids[0].stroke-width: expected a non-negative number, got -1 at line 2 column 34"
+        );
+        let err = GridError {
+            location: None,
+            ..err
+        };
+        assert_eq!(
+            err.to_string(),
+            "grids[0].stroke-width: expected a non-negative number, got -1"
+        );
+    }
+}
@@ -0,0 +1,243 @@
+use serde::{Deserialize, Serialize};
+
+use crate::error::{color, positive, ErrorKind, GridError};
+
+/// How to fill the faces formed by the lines of the grids, which are drawn behind all grids. Each
+/// face is identified by the indices of the lines it lies between, one for each grid of parallel
+/// lines in the collection; see `Face::indices`. In YAML, the rule is chosen by a `rule` field.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(tag = "rule", rename_all = "kebab-case", deny_unknown_fields)]
+pub enum Fill {
+    /// Alternates between two colors by whether the sum of the indices is even or odd
+    Checkerboard { colors: [String; 2] },
+    /// Cycles through colors by the sum of the indices, each multiplied by its grid's weight
+    Palette {
+        colors: Vec<String>,
+        /// Weight of each grid of parallel lines. All weights are 1 if unset.
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        weights: Vec<i64>,
+    },
+    /// Looks up the indices in a table of colors, after reducing each modulo its grid's period.
+    /// Faces which aren't in the table are left unfilled.
+    Indices {
+        /// Period of each grid of parallel lines
+        period: Vec<i64>,
+        colors: Vec<IndexColor>,
+    },
+}
+
+/// An entry in the table of colors of `Fill::Indices`.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct IndexColor {
+    pub indices: Vec<i64>,
+    pub color: String,
+}
+
+impl Fill {
+    /// The color of the face with the given indices, or `None` to leave it unfilled.
+    pub fn color(&self, indices: &[i64]) -> Option<&str> {
+        match self {
+            Fill::Checkerboard { colors } => {
+                let sum = indices.iter().sum::<i64>();
+                Some(&colors[sum.rem_euclid(2) as usize])
+            }
+            Fill::Palette { colors, weights } => {
+                let sum = indices
+                    .iter()
+                    .enumerate()
+                    .map(|(i, index)| index * weights.get(i).copied().unwrap_or(1))
+                    .sum::<i64>();
+                Some(&colors[sum.rem_euclid(colors.len() as i64) as usize])
+            }
+            Fill::Indices { period, colors } => {
+                let reduced = indices
+                    .iter()
+                    .zip(period)
+                    .map(|(index, period)| index.rem_euclid(*period))
+                    .collect::<Vec<_>>();
+                colors
+                    .iter()
+                    .find(|entry| entry.indices == reduced)
+                    .map(|entry| &*entry.color)
+            }
+        }
+    }
+
+    /// Checks the fill, for a collection with `grids` grids of parallel lines.
+    pub(crate) fn validate(&self, field: &str, grids: usize) -> Result<(), GridError> {
+        let error = |name: String, kind| GridError::new(format!("{}.{}", field, name), kind);
+        let length = |name: String, values: usize| {
+            if values == grids {
+                Ok(())
+            } else {
+                Err(error(
+                    name,
+                    ErrorKind::WrongLength {
+                        expected: grids,
+                        value: values,
+                    },
+                ))
+            }
+        };
+        match self {
+            Fill::Checkerboard { colors } => {
+                for (i, value) in colors.iter().enumerate() {
+                    color(value).map_err(|kind| error(format!("colors.{}", i), kind))?;
+                }
+            }
+            Fill::Palette { colors, weights } => {
+                if colors.is_empty() {
+                    return Err(error(
+                        "colors".into(),
+                        ErrorKind::TooSmall {
+                            min: 1.0,
+                            value: 0.0,
+                        },
+                    ));
+                }
+                for (i, value) in colors.iter().enumerate() {
+                    color(value).map_err(|kind| error(format!("colors.{}", i), kind))?;
+                }
+                if !weights.is_empty() {
+                    length("weights".into(), weights.len())?;
+                }
+            }
+            Fill::Indices { period, colors } => {
+                length("period".into(), period.len())?;
+                for (i, &value) in period.iter().enumerate() {
+                    positive(value as f64).map_err(|kind| error(format!("period.{}", i), kind))?;
+                }
+                for (i, entry) in colors.iter().enumerate() {
+                    let name = format!("colors.{}", i);
+                    length(format!("{}.indices", name), entry.indices.len())?;
+                    color(&entry.color).map_err(|kind| error(format!("{}.color", name), kind))?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn colors<const N: usize>(colors: [&str; N]) -> Vec<String> {
+        colors.map(String::from).to_vec()
+    }
+
+    fn index_color(indices: Vec<i64>, color: &str) -> IndexColor {
+        IndexColor {
+            indices,
+            color: color.into(),
+        }
+    }
+
+    #[test]
+    fn checkerboard_alternates() {
+        let fill = Fill::Checkerboard {
+            colors: ["black".into(), "white".into()],
+        };
+        assert_eq!(fill.color(&[0, 0]), Some("black"));
+        assert_eq!(fill.color(&[1, 0]), Some("white"));
+        assert_eq!(fill.color(&[1, 1]), Some("black"));
+        assert_eq!(fill.color(&[-1, 0]), Some("white"));
+    }
+
+    #[test]
+    fn palette_cycles_by_weighted_sum() {
+        let palette = |weights| Fill::Palette {
+            colors: colors(["red", "green", "blue"]),
+            weights,
+        };
+        let fill = palette(vec![]);
+        assert_eq!(fill.color(&[1, 1]), Some("blue"));
+        assert_eq!(fill.color(&[-1, 0]), Some("blue"));
+        let fill = palette(vec![1, 2]);
+        assert_eq!(fill.color(&[1, 1]), Some("red"));
+        assert_eq!(fill.color(&[0, 1]), Some("blue"));
+    }
+
+    #[test]
+    fn indices_are_looked_up_modulo_the_period() {
+        let fill = Fill::Indices {
+            period: vec![2, 3],
+            colors: vec![
+                index_color(vec![0, 0], "red"),
+                index_color(vec![1, 2], "blue"),
+            ],
+        };
+        assert_eq!(fill.color(&[2, 3]), Some("red"));
+        assert_eq!(fill.color(&[-1, -1]), Some("blue"));
+        assert_eq!(fill.color(&[0, 1]), None);
+    }
+
+    #[test]
+    fn rejects_bad_fills() {
+        let error = |fill: Fill| {
+            let error = fill.validate("fill", 2).unwrap_err();
+            (error.field, error.kind)
+        };
+        assert_eq!(
+            error(Fill::Checkerboard {
+                colors: ["black".into(), "nope".into()],
+            }),
+            (
+                "fill.colors.1".into(),
+                ErrorKind::InvalidColor("nope".into())
+            )
+        );
+        assert_eq!(
+            error(Fill::Palette {
+                colors: vec![],
+                weights: vec![],
+            }),
+            (
+                "fill.colors".into(),
+                ErrorKind::TooSmall {
+                    min: 1.0,
+                    value: 0.0
+                }
+            )
+        );
+        assert_eq!(
+            error(Fill::Palette {
+                colors: colors(["red"]),
+                weights: vec![1],
+            }),
+            (
+                "fill.weights".into(),
+                ErrorKind::WrongLength {
+                    expected: 2,
+                    value: 1
+                }
+            )
+        );
+        assert_eq!(
+            error(Fill::Indices {
+                period: vec![2, 0],
+                colors: vec![],
+            }),
+            ("fill.period.1".into(), ErrorKind::NotPositive(0.0))
+        );
+        assert_eq!(
+            error(Fill::Indices {
+                period: vec![2, 2],
+                colors: vec![index_color(vec![0, 0], "red"), index_color(vec![1], "blue")],
+            }),
+            (
+                "fill.colors.1.indices".into(),
+                ErrorKind::WrongLength {
+                    expected: 2,
+                    value: 1
+                }
+            )
+        );
+        let fill = Fill::Palette {
+            colors: colors(["red", "#00ff00"]),
+            weights: vec![1, 2],
+        };
+        assert_eq!(fill.validate("fill", 2), Ok(()));
+    }
+}
@@ -0,0 +1,280 @@
+use std::ops::{Add, Mul, Neg, Sub};
+
+use serde::{Deserialize, Serialize};
+
+use crate::units::{Conversion, Length, ResolveUnits};
+
+/// A point, or a vector between two points. May be deserialized from `[x, y]` as well as
+/// `{x: x, y: y}`. Points read from a collection have coordinates of type `Length`, and are used
+/// as `Point<f64>` once their units are resolved.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Deserialize, Serialize)]
+#[serde(from = "PointRepr<T>", bound(deserialize = "T: Deserialize<'de>"))]
+pub struct Point<T = f64> {
+    pub x: T,
+    pub y: T,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum PointRepr<T> {
+    Pair(T, T),
+    Fields { x: T, y: T },
+}
+
+impl<T> From<PointRepr<T>> for Point<T> {
+    fn from(repr: PointRepr<T>) -> Self {
+        match repr {
+            PointRepr::Pair(x, y) | PointRepr::Fields { x, y } => Point { x, y },
+        }
+    }
+}
+
+impl<T> Point<T> {
+    /// Applies `f` to both coordinates.
+    pub fn map<U>(self, mut f: impl FnMut(T) -> U) -> Point<U> {
+        Point {
+            x: f(self.x),
+            y: f(self.y),
+        }
+    }
+}
+
+impl Point<Length> {
+    /// The point in the units of the collection; see `Length::get`.
+    pub fn get(self) -> Point {
+        self.map(Length::get)
+    }
+}
+
+impl From<Point> for Point<Length> {
+    fn from(point: Point) -> Self {
+        point.map(Length::new)
+    }
+}
+
+impl<T: ResolveUnits> ResolveUnits for Point<T> {
+    fn resolve_units(&mut self, units: &mut Conversion) {
+        self.x.resolve_units(units);
+        self.y.resolve_units(units);
+    }
+}
+
+impl Point {
+    pub fn new(x: f64, y: f64) -> Self {
+        Point { x, y }
+    }
+
+    pub fn dot(self, other: Point) -> f64 {
+        self.x * other.x + self.y * other.y
+    }
+
+    /// The z-component of the cross product of the two vectors.
+    pub fn cross(self, other: Point) -> f64 {
+        self.x * other.y - self.y * other.x
+    }
+
+    pub fn length(self) -> f64 {
+        self.x.hypot(self.y)
+    }
+
+    pub fn distance(self, other: Point) -> f64 {
+        (other - self).length()
+    }
+}
+
+impl Add for Point {
+    type Output = Point;
+
+    fn add(self, other: Point) -> Point {
+        Point::new(self.x + other.x, self.y + other.y)
+    }
+}
+
+impl Sub for Point {
+    type Output = Point;
+
+    fn sub(self, other: Point) -> Point {
+        Point::new(self.x - other.x, self.y - other.y)
+    }
+}
+
+impl Neg for Point {
+    type Output = Point;
+
+    fn neg(self) -> Point {
+        Point::new(-self.x, -self.y)
+    }
+}
+
+impl Mul<f64> for Point {
+    type Output = Point;
+
+    fn mul(self, scale: f64) -> Point {
+        Point::new(self.x * scale, self.y * scale)
+    }
+}
+
+impl From<(f64, f64)> for Point {
+    fn from((x, y): (f64, f64)) -> Self {
+        Point::new(x, y)
+    }
+}
+
+/// A straight line segment. May be deserialized from `[start, end]` as well as
+/// `{start: start, end: end}`.
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(from = "SegmentRepr<T>", bound(deserialize = "T: Deserialize<'de>"))]
+pub struct Segment<T = f64> {
+    pub start: Point<T>,
+    pub end: Point<T>,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged, bound(deserialize = "T: Deserialize<'de>"))]
+enum SegmentRepr<T> {
+    Pair(Point<T>, Point<T>),
+    Fields { start: Point<T>, end: Point<T> },
+}
+
+impl<T> From<SegmentRepr<T>> for Segment<T> {
+    fn from(repr: SegmentRepr<T>) -> Self {
+        match repr {
+            SegmentRepr::Pair(start, end) | SegmentRepr::Fields { start, end } => {
+                Segment { start, end }
+            }
+        }
+    }
+}
+
+impl Segment<Length> {
+    /// The segment in the units of the collection; see `Length::get`.
+    pub fn get(self) -> Segment {
+        Segment {
+            start: self.start.get(),
+            end: self.end.get(),
+        }
+    }
+}
+
+impl From<Segment> for Segment<Length> {
+    fn from(segment: Segment) -> Self {
+        Segment {
+            start: segment.start.into(),
+            end: segment.end.into(),
+        }
+    }
+}
+
+impl<T: ResolveUnits> ResolveUnits for Segment<T> {
+    fn resolve_units(&mut self, units: &mut Conversion) {
+        self.start.resolve_units(units);
+        self.end.resolve_units(units);
+    }
+}
+
+impl Segment {
+    pub fn new(start: impl Into<Point>, end: impl Into<Point>) -> Self {
+        Segment {
+            start: start.into(),
+            end: end.into(),
+        }
+    }
+
+    pub fn length(&self) -> f64 {
+        self.start.distance(self.end)
+    }
+
+    /// The same segment, traversed in the opposite direction.
+    pub fn reversed(self) -> Self {
+        Segment {
+            start: self.end,
+            end: self.start,
+        }
+    }
+
+    /// The point at parameter `t` along the segment, where 0 is the start and 1 is the end.
+    pub fn at(&self, t: f64) -> Point {
+        self.start + (self.end - self.start) * t
+    }
+}
+
+/// An arc of a circle, or a whole circle. Angles are in degrees, measured clockwise from the
+/// positive x-axis, as for `Grid::theta`.
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize, Serialize)]
+pub struct Arc {
+    pub center: Point,
+    pub radius: f64,
+    /// Angle of the start of the arc
+    pub start: f64,
+    /// Angle from the start to the end of the arc, clockwise, in the range 0..=360
+    pub sweep: f64,
+}
+
+impl Arc {
+    pub fn new(center: impl Into<Point>, radius: f64, start: f64, sweep: f64) -> Self {
+        Arc {
+            center: center.into(),
+            radius,
+            start,
+            sweep,
+        }
+    }
+
+    /// A whole circle, starting from the positive x-axis.
+    pub fn circle(center: impl Into<Point>, radius: f64) -> Self {
+        Arc::new(center, radius, 0.0, 360.0)
+    }
+
+    pub fn is_circle(&self) -> bool {
+        self.sweep >= 360.0
+    }
+
+    /// The point on the circle at angle `angle`.
+    pub fn at(&self, angle: f64) -> Point {
+        let angle = angle.to_radians();
+        self.center + Point::new(angle.cos(), angle.sin()) * self.radius
+    }
+
+    pub fn start_point(&self) -> Point {
+        self.at(self.start)
+    }
+
+    pub fn end_point(&self) -> Point {
+        self.at(self.start + self.sweep)
+    }
+
+    /// Approximates the arc with cubic Bézier curves, each spanning at most 90 degrees. Each curve
+    /// is given as its start point, two control points and end point.
+    pub fn beziers(&self) -> Vec<[Point; 4]> {
+        let count = (self.sweep / 90.0).ceil().max(1.0) as usize;
+        let step = self.sweep / count as f64;
+        // distance of the control points from the ends, relative to the radius
+        let k = 4.0 / 3.0 * (step.to_radians() / 4.0).tan();
+        let tangent = |angle: f64| {
+            let angle = angle.to_radians();
+            Point::new(-angle.sin(), angle.cos()) * (k * self.radius)
+        };
+        (0..count)
+            .map(|i| {
+                let a0 = self.start + step * i as f64;
+                let a1 = a0 + step;
+                let (p0, p3) = (self.at(a0), self.at(a1));
+                [p0, p0 + tangent(a0), p3 - tangent(a1), p3]
+            })
+            .collect()
+    }
+
+    /// Approximates the arc with a polyline which deviates from it by at most `tolerance`.
+    pub fn flatten(&self, tolerance: f64) -> Vec<Point> {
+        // the largest angle whose chord stays within `tolerance` of the arc
+        let max_step = if tolerance < self.radius {
+            2.0 * (1.0 - tolerance / self.radius).acos().to_degrees()
+        } else {
+            90.0
+        };
+        let count = (self.sweep / max_step.clamp(1e-3, 90.0)).ceil().max(1.0) as usize;
+        (0..=count)
+            .map(|i| self.at(self.start + self.sweep * i as f64 / count as f64))
+            .collect()
+    }
+}
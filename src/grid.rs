@@ -0,0 +1,575 @@
+use std::{f64::consts::FRAC_1_SQRT_2, fmt, marker::PhantomData};
+
+use serde::{
+    de::{
+        value::{MapAccessDeserializer, SeqAccessDeserializer},
+        MapAccess, SeqAccess, Visitor,
+    },
+    Deserialize, Deserializer, Serialize,
+};
+
+use crate::{
+    error::{color, finite, non_negative, positive, ErrorKind, GridError},
+    geometry::{Point, Segment},
+    units::{Conversion, Length, ResolveUnits},
+    Rect, Region,
+};
+
+#[derive(Clone, Debug, Default, PartialEq, Deserialize, Serialize)]
+#[serde(default, rename_all = "kebab-case", deny_unknown_fields)]
+pub struct Grid {
+    /// Name of the grid, used for layer names in formats which have them
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    /// x-coordinate of the center of the grid
+    pub cx: Length,
+    /// y-coordinate of the center of the grid
+    pub cy: Length,
+    /// Spacing between adjacent lines
+    pub step: Length,
+    /// Position of the center point relative to the grid. Integers mean the center point is on a
+    /// grid line, non-integers mean it is somewhere between two grid lines.
+    pub center_position: f64,
+    /// Rotation clockwise from vertical about the center point, in degrees
+    pub theta: f64,
+    /// Stroke color
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stroke: Option<String>,
+    /// Stroke width
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stroke_width: Option<Length>,
+    /// Tiers of major lines, which are drawn with their own style instead of the grid's. May be
+    /// given as a single tier or a list.
+    #[serde(
+        deserialize_with = "one_or_many",
+        skip_serializing_if = "Vec::is_empty"
+    )]
+    pub major: Vec<MajorLines>,
+    /// Whether to leave out the lines of the grid, which can still be used for markers
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub hidden: bool,
+    /// Where to place the lines within the viewable area, overriding `cx`, `cy` and
+    /// `center-position`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub align: Option<Align>,
+    /// Region the lines are limited to, within the viewable area. Outside it, the grid doesn't
+    /// divide the faces used for fills.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub region: Option<Region>,
+}
+
+/// How to place the lines of a grid within the viewable area, along the direction `theta`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Align {
+    /// A line on the edge the grid starts from: the left edge for vertical lines, or the top edge
+    /// for horizontal ones
+    Start,
+    /// Lines placed symmetrically, with equal space before the first and after the last
+    Center,
+    /// A line on the edge opposite to `Start`
+    End,
+    /// Lines on both edges, with the step adjusted to fit a whole number of steps between them
+    EdgeToEdge,
+}
+
+/// A subset of the lines of a grid, drawn with a different style, such as every eighth line being
+/// drawn heavier. Unset style fields are inherited from the grid.
+#[derive(Clone, Debug, Default, PartialEq, Deserialize, Serialize)]
+#[serde(default, rename_all = "kebab-case", deny_unknown_fields)]
+pub struct MajorLines {
+    /// Number of steps between major lines
+    pub every: u32,
+    /// Index of one of the major lines; see `Grid::lines`
+    pub offset: i64,
+    /// Stroke color
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stroke: Option<String>,
+    /// Stroke width
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stroke_width: Option<Length>,
+}
+
+impl MajorLines {
+    /// Every `every`th line, starting from line 0.
+    pub fn new(every: u32) -> Self {
+        MajorLines {
+            every,
+            ..Default::default()
+        }
+    }
+
+    pub fn with_offset(mut self, offset: i64) -> Self {
+        self.offset = offset;
+        self
+    }
+
+    pub fn with_stroke(mut self, stroke: impl Into<String>) -> Self {
+        self.stroke = Some(stroke.into());
+        self
+    }
+
+    pub fn with_stroke_width(mut self, stroke_width: f64) -> Self {
+        self.stroke_width = Some(stroke_width.into());
+        self
+    }
+
+    /// Whether line `index` is one of these major lines.
+    pub fn contains(&self, index: i64) -> bool {
+        self.every != 0 && (index - self.offset).rem_euclid(self.every as i64) == 0
+    }
+
+    fn validate(&self) -> Result<(), GridError> {
+        if self.every == 0 {
+            return Err(GridError::new("every", ErrorKind::NotPositive(0.0)));
+        }
+        if let Some(stroke) = &self.stroke {
+            color(stroke).map_err(|kind| GridError::new("stroke", kind))?;
+        }
+        if let Some(width) = self.stroke_width {
+            non_negative(width.get()).map_err(|kind| GridError::new("stroke-width", kind))?;
+        }
+        Ok(())
+    }
+}
+
+impl ResolveUnits for MajorLines {
+    fn resolve_units(&mut self, units: &mut Conversion) {
+        self.stroke_width.resolve_units(units);
+    }
+}
+
+/// Deserializes either a single mapping or a list of them.
+pub(crate) fn one_or_many<'de, D, T>(deserializer: D) -> Result<Vec<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    struct OneOrMany<T>(PhantomData<T>);
+
+    impl<'de, T: Deserialize<'de>> Visitor<'de> for OneOrMany<T> {
+        type Value = Vec<T>;
+
+        fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            f.write_str("a mapping or a list of them")
+        }
+
+        fn visit_map<A: MapAccess<'de>>(self, map: A) -> Result<Self::Value, A::Error> {
+            T::deserialize(MapAccessDeserializer::new(map)).map(|value| vec![value])
+        }
+
+        fn visit_seq<A: SeqAccess<'de>>(self, seq: A) -> Result<Self::Value, A::Error> {
+            Vec::deserialize(SeqAccessDeserializer::new(seq))
+        }
+    }
+
+    deserializer.deserialize_any(OneOrMany(PhantomData))
+}
+
+impl Grid {
+    /// Creates a grid of vertical lines spaced `step` apart, with a line through the origin.
+    pub fn new(step: f64) -> Self {
+        Grid {
+            step: step.into(),
+            ..Default::default()
+        }
+    }
+
+    pub fn with_name(mut self, name: impl Into<String>) -> Self {
+        self.name = Some(name.into());
+        self
+    }
+
+    /// Sets the center point of the grid.
+    pub fn with_center(mut self, cx: f64, cy: f64) -> Self {
+        self.cx = cx.into();
+        self.cy = cy.into();
+        self
+    }
+
+    /// Sets the position of the center point relative to the grid lines.
+    pub fn with_center_position(mut self, center_position: f64) -> Self {
+        self.center_position = center_position;
+        self
+    }
+
+    /// Sets the rotation of the grid, in degrees clockwise from vertical.
+    pub fn with_theta(mut self, theta: f64) -> Self {
+        self.theta = theta;
+        self
+    }
+
+    pub fn with_stroke(mut self, stroke: impl Into<String>) -> Self {
+        self.stroke = Some(stroke.into());
+        self
+    }
+
+    pub fn with_stroke_width(mut self, stroke_width: f64) -> Self {
+        self.stroke_width = Some(stroke_width.into());
+        self
+    }
+
+    /// Adds a tier of major lines.
+    pub fn with_major(mut self, major: MajorLines) -> Self {
+        self.major.push(major);
+        self
+    }
+
+    /// Sets whether to leave out the lines of the grid.
+    pub fn with_hidden(mut self, hidden: bool) -> Self {
+        self.hidden = hidden;
+        self
+    }
+
+    /// Places the lines within `area`; see `align`.
+    pub fn with_align(mut self, align: Align) -> Self {
+        self.align = Some(align);
+        self
+    }
+
+    /// Limits the lines to `region`.
+    pub fn with_region(mut self, region: Region) -> Self {
+        self.region = Some(region);
+        self
+    }
+
+    /// Returns the same lines with the alignment resolved for `area`: line 0 is moved to the
+    /// first line of the alignment, and `align` is cleared. Grids without an alignment are
+    /// returned unchanged.
+    pub fn aligned(&self, area: Rect) -> Grid {
+        let align = match self.align {
+            Some(align) => align,
+            None => return self.clone(),
+        };
+        let (theta, _) = self.line_position(0);
+        let (cos, sin) = cos_sin_degrees(theta);
+        // range covered by the area along the normal of the lines
+        let along = [
+            (area.min_x, area.min_y),
+            (area.max_x, area.min_y),
+            (area.min_x, area.max_y),
+            (area.max_x, area.max_y),
+        ]
+        .map(|(x, y)| cos * x + sin * y);
+        let start = along.into_iter().fold(f64::INFINITY, f64::min);
+        let end = along.into_iter().fold(f64::NEG_INFINITY, f64::max);
+        let length = end - start;
+        let mut step = self.step.get();
+        let first = match align {
+            Align::Start => start,
+            Align::End => end,
+            Align::Center => {
+                let steps = (length / step + 1e-9).floor();
+                start + (length - steps * step) / 2.0
+            }
+            Align::EdgeToEdge => {
+                step = length / (length / step).round().max(1.0);
+                start
+            }
+        };
+        Grid {
+            cx: (first * cos).into(),
+            cy: (first * sin).into(),
+            step: step.into(),
+            center_position: 0.0,
+            align: None,
+            ..self.clone()
+        }
+    }
+
+    /// Returns the tier of major lines which line `index` belongs to, as an index into `major`, or
+    /// `None` for a regular line. A line belonging to several tiers is drawn by the one with the
+    /// largest spacing, or the last of those.
+    pub fn tier(&self, index: i64) -> Option<usize> {
+        self.major
+            .iter()
+            .enumerate()
+            .filter(|(_, major)| major.contains(index))
+            .max_by_key(|(_, major)| major.every)
+            .map(|(tier, _)| tier)
+    }
+
+    /// Returns the lines of this grid which cross `area`, clipped to it, in order of increasing
+    /// position. Each line is paired with its index: line `i` lies `i - center_position` steps
+    /// from the center point, in the direction `theta` degrees clockwise from the positive x-axis.
+    ///
+    /// The grid must be valid; see `GridCollection::validate`.
+    pub fn lines(&self, area: Rect) -> impl Iterator<Item = (i64, Segment)> {
+        let mut theta = self.theta.rem_euclid(360.0);
+        let mut step = self.step.get();
+        if theta >= 180.0 {
+            theta -= 180.0;
+            step = -step;
+        };
+        let (cos, sin) = cos_sin_degrees(theta);
+        let cx = self.cx.get() - cos * step * self.center_position;
+        let cy = self.cy.get() - sin * step * self.center_position;
+
+        // each line `i` runs from `start + offset * i` to `end + offset * i`
+        let (start, end, offset, min_idx, max_idx, direction);
+        if (45.0..135.0).contains(&theta) {
+            // more horizontal than vertical
+            debug_assert!(sin >= FRAC_1_SQRT_2);
+            let cot = cos / sin;
+            // project onto the min_x line
+            let y0 = cy + cot * (cx - area.min_x);
+            // project onto the max_x line
+            let y1 = cy + cot * (cx - area.max_x);
+            let dy = (step / sin).abs();
+            min_idx = ((area.min_y - y0.max(y1)) / dy - 1.0) as i64;
+            max_idx = ((area.max_y - y0.min(y1)) / dy + 1.0) as i64;
+            start = Point::new(area.min_x, y0);
+            end = Point::new(area.max_x, y1);
+            offset = Point::new(0.0, dy);
+            direction = step.signum();
+        } else {
+            // more vertical than horizontal
+            debug_assert!(cos.abs() >= FRAC_1_SQRT_2);
+            let tan = sin / cos;
+            // project onto the min_y line
+            let x0 = cx + tan * (cy - area.min_y);
+            // project onto the max_y line
+            let x1 = cx + tan * (cy - area.max_y);
+            let dx = (step / cos).abs();
+            min_idx = ((area.min_x - x0.max(x1)) / dx - 1.0) as i64;
+            max_idx = ((area.max_x - x0.min(x1)) / dx + 1.0) as i64;
+            start = Point::new(x0, area.min_y);
+            end = Point::new(x1, area.max_y);
+            offset = Point::new(dx, 0.0);
+            direction = (step * cos).signum();
+        }
+        (min_idx..=max_idx).filter_map(move |i| {
+            let shift = offset * (i as f64);
+            let segment = area.clip_segment(Segment::new(start + shift, end + shift))?;
+            Some((i * direction as i64, segment))
+        })
+    }
+
+    /// Returns the lines of this grid which cross `area` like `lines`, but limited to the grid's
+    /// region, which may split a line into several segments or leave it out entirely.
+    ///
+    /// The grid must be valid; see `GridCollection::validate`.
+    pub fn segments(&self, area: Rect) -> impl Iterator<Item = (i64, Segment)> + '_ {
+        self.lines(area).flat_map(move |(index, segment)| {
+            let pieces = match &self.region {
+                Some(region) => region.clip_segment(segment),
+                None => vec![segment],
+            };
+            pieces.into_iter().map(move |piece| (index, piece))
+        })
+    }
+
+    /// Whether `point` lies within the grid's region, if it has one.
+    pub fn covers(&self, point: Point) -> bool {
+        self.region
+            .as_ref()
+            .is_none_or(|region| region.contains(point))
+    }
+
+    /// Returns the position of line `index` (see `lines`) as the angle of its normal, in degrees in
+    /// the range 0..180, and its signed distance from the origin along that normal.
+    pub fn line_position(&self, index: i64) -> (f64, f64) {
+        let theta = self.theta.rem_euclid(360.0);
+        let (theta, sign) = if theta >= 180.0 {
+            (theta - 180.0, -1.0)
+        } else {
+            (theta, 1.0)
+        };
+        let (cos, sin) = cos_sin_degrees(theta);
+        let center = cos * self.cx.get() + sin * self.cy.get();
+        let offset = center + sign * (index as f64 - self.center_position) * self.step.get();
+        (theta, offset)
+    }
+
+    /// Whether this grid has a line at the position `(theta, offset)`, as returned by
+    /// `line_position`, to within `tolerance` in distance and `angle_tolerance` degrees in angle.
+    pub fn has_line(&self, theta: f64, offset: f64, tolerance: f64, angle_tolerance: f64) -> bool {
+        let (theta0, offset0) = self.line_position(0);
+        let difference = (theta - theta0).rem_euclid(180.0);
+        if difference.min(180.0 - difference) > angle_tolerance {
+            return false;
+        }
+        // angles on either side of 0 have opposite normals
+        let offset = if (theta - theta0).abs() > 90.0 {
+            -offset
+        } else {
+            offset
+        };
+        let step = self.line_position(1).1 - offset0;
+        let steps = (offset - offset0) / step;
+        (steps - steps.round()).abs() * step.abs() <= tolerance
+    }
+
+    pub(crate) fn validate(&self) -> Result<(), GridError> {
+        for (name, value) in [
+            ("cx", self.cx.get()),
+            ("cy", self.cy.get()),
+            ("center-position", self.center_position),
+            ("theta", self.theta),
+        ] {
+            finite(value).map_err(|kind| GridError::new(name, kind))?;
+        }
+        positive(self.step.get()).map_err(|kind| GridError::new("step", kind))?;
+        if let Some(stroke) = &self.stroke {
+            color(stroke).map_err(|kind| GridError::new("stroke", kind))?;
+        }
+        if let Some(width) = self.stroke_width {
+            non_negative(width.get()).map_err(|kind| GridError::new("stroke-width", kind))?;
+        }
+        if let Some(region) = &self.region {
+            region.validate("region")?;
+        }
+        for (i, major) in self.major.iter().enumerate() {
+            major.validate().map_err(|mut err| {
+                err.field = format!("major.{}.{}", i, err.field);
+                err
+            })?;
+        }
+        Ok(())
+    }
+}
+
+impl ResolveUnits for Grid {
+    fn resolve_units(&mut self, units: &mut Conversion) {
+        self.cx.resolve_units(units);
+        self.cy.resolve_units(units);
+        self.step.resolve_units(units);
+        self.stroke_width.resolve_units(units);
+        self.major.resolve_units(units);
+        self.region.resolve_units(units);
+    }
+}
+
+/// Returns the cos and sin of an angle in degrees, using exact values for multiples of 45 degrees
+/// in the range 0..180
+pub(crate) fn cos_sin_degrees(theta: f64) -> (f64, f64) {
+    if theta == 0.0 {
+        (1.0, 0.0)
+    } else if theta == 45.0 {
+        (FRAC_1_SQRT_2, FRAC_1_SQRT_2)
+    } else if theta == 90.0 {
+        (0.0, 1.0)
+    } else if theta == 135.0 {
+        (-FRAC_1_SQRT_2, FRAC_1_SQRT_2)
+    } else {
+        let rad = theta.to_radians();
+        (rad.cos(), rad.sin())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const AREA: Rect = Rect {
+        min_x: 0.0,
+        max_x: 100.0,
+        min_y: 0.0,
+        max_y: 100.0,
+    };
+
+    /// Returns the index and x coordinate of each vertical line of `grid` across `AREA`.
+    fn columns(grid: &Grid) -> Vec<(i64, f64)> {
+        grid.lines(AREA)
+            .map(|(index, segment)| {
+                assert_eq!(segment.start.x, segment.end.x);
+                (index, segment.start.x)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn tiers_prefer_the_widest_spacing() {
+        let grid = Grid::new(1.0)
+            .with_major(MajorLines::new(5))
+            .with_major(MajorLines::new(10))
+            .with_major(MajorLines::new(5).with_offset(2));
+        assert_eq!(grid.tier(3), None);
+        assert_eq!(grid.tier(5), Some(0));
+        assert_eq!(grid.tier(-5), Some(0));
+        assert_eq!(grid.tier(0), Some(1));
+        assert_eq!(grid.tier(-20), Some(1));
+        assert_eq!(grid.tier(12), Some(2));
+        // of tiers with the same spacing, the last wins
+        let grid = Grid::new(1.0)
+            .with_major(MajorLines::new(5))
+            .with_major(MajorLines::new(5).with_stroke("red"));
+        assert_eq!(grid.tier(5), Some(1));
+    }
+
+    #[test]
+    fn lines_count_along_theta() {
+        let grid = Grid::new(25.0).with_center(50.0, 50.0);
+        assert_eq!(
+            columns(&grid),
+            vec![(-2, 0.0), (-1, 25.0), (0, 50.0), (1, 75.0), (2, 100.0)]
+        );
+        // turned around, the indices count the other way, but the lines are the same
+        assert_eq!(
+            columns(&grid.clone().with_theta(180.0)),
+            vec![(2, 0.0), (1, 25.0), (0, 50.0), (-1, 75.0), (-2, 100.0)]
+        );
+        let grid = grid.with_center_position(1.0);
+        assert_eq!(columns(&grid)[0], (-1, 0.0));
+        assert_eq!(columns(&grid.with_theta(540.0))[0], (3, 0.0));
+    }
+
+    #[test]
+    fn lines_agree_with_their_positions() {
+        for theta in [0.0, 30.0, 90.0, 150.0, 180.0, 210.0, 270.0, 330.0] {
+            let grid = Grid::new(7.0)
+                .with_center(20.0, 30.0)
+                .with_center_position(0.5)
+                .with_theta(theta);
+            let mut count = 0;
+            for (index, segment) in grid.lines(AREA) {
+                let (normal, offset) = grid.line_position(index);
+                assert!((0.0..180.0).contains(&normal));
+                let (cos, sin) = cos_sin_degrees(normal);
+                for point in [segment.start, segment.end] {
+                    let distance = cos * point.x + sin * point.y - offset;
+                    assert!(distance.abs() < 1e-9, "theta {theta}, line {index}");
+                }
+                count += 1;
+            }
+            assert!(count > 10, "theta {theta}");
+        }
+    }
+
+    #[test]
+    fn has_line_matches_positions() {
+        let grid = Grid::new(10.0).with_center(5.0, 0.0).with_theta(30.0);
+        let (theta, offset) = grid.line_position(3);
+        assert!(grid.has_line(theta, offset, 1e-6, 1e-6));
+        assert!(grid.has_line(theta, offset + 100.0, 1e-6, 1e-6));
+        assert!(!grid.has_line(theta, offset + 5.0, 1e-6, 1e-6));
+        assert!(grid.has_line(theta, offset + 0.5, 1.0, 1e-6));
+        assert!(!grid.has_line(theta + 1.0, offset, 1e-6, 1e-6));
+        assert!(grid.has_line(theta + 1.0, offset, 1e-6, 2.0));
+        // the same lines, numbered from the other side
+        let turned = grid.clone().with_theta(210.0);
+        assert!(turned.has_line(theta, offset, 1e-6, 1e-6));
+        // lines just either side of vertical have opposite normals
+        let vertical = Grid::new(10.0).with_center(2.0, 0.0).with_theta(0.5);
+        assert!(vertical.has_line(179.8, -12.0, 0.1, 1.0));
+        assert!(!vertical.has_line(179.8, -8.0, 0.1, 1.0));
+    }
+
+    #[test]
+    fn aligns_to_the_area() {
+        let grid = Grid::new(30.0).with_center(7.0, 7.0);
+        let first = |align| {
+            let grid = grid.clone().with_align(align).aligned(AREA);
+            assert_eq!(grid.align, None);
+            (columns(&grid)[0], grid.step.get())
+        };
+        assert_eq!(first(Align::Start), ((0, 0.0), 30.0));
+        assert_eq!(first(Align::End), ((-3, 10.0), 30.0));
+        assert_eq!(first(Align::Center), ((0, 5.0), 30.0));
+        let ((index, x), step) = first(Align::EdgeToEdge);
+        assert_eq!((index, x), (0, 0.0));
+        assert!((step - 100.0 / 3.0).abs() < 1e-9);
+        // without an alignment nothing moves
+        assert_eq!(grid.aligned(AREA), grid);
+    }
+}
@@ -0,0 +1,298 @@
+use std::f64::consts::PI;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    error::{color, finite, non_negative, positive, GridError},
+    tiling::PeriodicEdges,
+    units::{Conversion, Length, ResolveUnits},
+    Point, Rect, Segment,
+};
+
+/// A tiling based on regular hexagons.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(default, rename_all = "kebab-case", deny_unknown_fields)]
+pub struct HexGrid {
+    /// Name of the grid, used for layer names in formats which have them
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    /// x-coordinate of the center of one of the hexagons
+    pub cx: Length,
+    /// y-coordinate of the center of one of the hexagons
+    pub cy: Length,
+    /// Length of the edges of the hexagons
+    pub size: Length,
+    pub orientation: HexOrientation,
+    /// Rotation clockwise about the center point, in degrees
+    pub theta: f64,
+    pub tiling: HexTiling,
+    /// Stroke color
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stroke: Option<String>,
+    /// Stroke width
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stroke_width: Option<Length>,
+}
+
+impl Default for HexGrid {
+    fn default() -> Self {
+        HexGrid {
+            name: None,
+            cx: Length::new(0.0),
+            cy: Length::new(0.0),
+            size: Length::new(1.0),
+            orientation: HexOrientation::default(),
+            theta: 0.0,
+            tiling: HexTiling::default(),
+            stroke: None,
+            stroke_width: None,
+        }
+    }
+}
+
+/// Which way up the hexagons are, before rotating by `HexGrid::theta`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum HexOrientation {
+    /// A vertex at the top and bottom of each hexagon
+    #[default]
+    Pointy,
+    /// An edge at the top and bottom of each hexagon
+    Flat,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum HexTiling {
+    /// Hexagons sharing edges with each other
+    #[default]
+    Hexagonal,
+    /// Hexagons each divided into three rhombi, from the center to every other vertex
+    Rhombille,
+    /// Hexagons meeting only at their vertices, with triangles in the gaps between them
+    Trihexagonal,
+}
+
+impl HexGrid {
+    /// Creates a hexagonal tiling with edges of length `size`, with a hexagon centered on the
+    /// origin.
+    pub fn new(size: f64) -> Self {
+        HexGrid {
+            size: size.into(),
+            ..Default::default()
+        }
+    }
+
+    pub fn with_name(mut self, name: impl Into<String>) -> Self {
+        self.name = Some(name.into());
+        self
+    }
+
+    /// Sets the center of one of the hexagons.
+    pub fn with_center(mut self, cx: f64, cy: f64) -> Self {
+        self.cx = cx.into();
+        self.cy = cy.into();
+        self
+    }
+
+    pub fn with_orientation(mut self, orientation: HexOrientation) -> Self {
+        self.orientation = orientation;
+        self
+    }
+
+    /// Sets the rotation of the tiling, in degrees clockwise.
+    pub fn with_theta(mut self, theta: f64) -> Self {
+        self.theta = theta;
+        self
+    }
+
+    pub fn with_tiling(mut self, tiling: HexTiling) -> Self {
+        self.tiling = tiling;
+        self
+    }
+
+    pub fn with_stroke(mut self, stroke: impl Into<String>) -> Self {
+        self.stroke = Some(stroke.into());
+        self
+    }
+
+    pub fn with_stroke_width(mut self, stroke_width: f64) -> Self {
+        self.stroke_width = Some(stroke_width.into());
+        self
+    }
+
+    /// Returns the edges of the tiling which cross `area`, clipped to it, with edges shared by
+    /// neighboring cells appearing only once.
+    ///
+    /// The grid must be valid; see `GridCollection::validate`.
+    pub fn segments(&self, area: Rect) -> Vec<Segment> {
+        self.cell().segments(area)
+    }
+
+    /// One cell of the tiling, centered on the origin, for flat hexagons.
+    fn cell(&self) -> PeriodicEdges {
+        let size = self.size.get();
+        let vertex = |k: usize| {
+            let angle = k as f64 * PI / 3.0;
+            Point::new(angle.cos(), angle.sin()) * size
+        };
+        let hexagon = (0..6).map(|k| Segment::new(vertex(k), vertex(k + 1)));
+        let spacing = match self.tiling {
+            HexTiling::Hexagonal | HexTiling::Rhombille => 3f64.sqrt() * size,
+            // hexagons touch at their vertices, rather than sharing edges
+            HexTiling::Trihexagonal => 2.0 * size,
+        };
+        // direction of the first neighbor: across an edge, or through a vertex
+        let angle = match self.tiling {
+            HexTiling::Hexagonal | HexTiling::Rhombille => PI / 6.0,
+            HexTiling::Trihexagonal => 0.0,
+        };
+        let neighbor = |angle: f64| Point::new(angle.cos(), angle.sin()) * spacing;
+        let mut edges = hexagon.collect::<Vec<_>>();
+        if self.tiling == HexTiling::Rhombille {
+            edges.extend([0, 2, 4].map(|k| Segment::new(Point::default(), vertex(k))));
+        }
+        let cell = PeriodicEdges {
+            edges,
+            a: neighbor(angle),
+            b: neighbor(angle + PI / 3.0),
+        };
+        let rotation = match self.orientation {
+            HexOrientation::Flat => self.theta,
+            HexOrientation::Pointy => self.theta + 30.0,
+        };
+        cell.transform(Point::new(self.cx.get(), self.cy.get()), rotation)
+    }
+
+    pub(crate) fn validate(&self) -> Result<(), GridError> {
+        for (name, value) in [
+            ("cx", self.cx.get()),
+            ("cy", self.cy.get()),
+            ("theta", self.theta),
+        ] {
+            finite(value).map_err(|kind| GridError::new(name, kind))?;
+        }
+        positive(self.size.get()).map_err(|kind| GridError::new("size", kind))?;
+        if let Some(stroke) = &self.stroke {
+            color(stroke).map_err(|kind| GridError::new("stroke", kind))?;
+        }
+        if let Some(width) = self.stroke_width {
+            non_negative(width.get()).map_err(|kind| GridError::new("stroke-width", kind))?;
+        }
+        Ok(())
+    }
+}
+
+impl ResolveUnits for HexGrid {
+    fn resolve_units(&mut self, units: &mut Conversion) {
+        self.cx.resolve_units(units);
+        self.cy.resolve_units(units);
+        self.size.resolve_units(units);
+        self.stroke_width.resolve_units(units);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vertices(cell: &PeriodicEdges) -> Vec<Point> {
+        let mut vertices: Vec<Point> = Vec::new();
+        for p in cell.edges.iter().flat_map(|edge| [edge.start, edge.end]) {
+            if vertices.iter().all(|q| q.distance(p) > 1e-9) {
+                vertices.push(p);
+            }
+        }
+        vertices
+    }
+
+    fn shared(a: &[Point], b: &[Point]) -> Vec<Point> {
+        a.iter()
+            .copied()
+            .filter(|&p| b.iter().any(|q| q.distance(p) < 1e-9))
+            .collect()
+    }
+
+    fn shifted(cell: &PeriodicEdges, shift: Point) -> Vec<Point> {
+        vertices(cell).into_iter().map(|p| p + shift).collect()
+    }
+
+    #[test]
+    fn orients_hexagons() {
+        for (orientation, corner) in [
+            (HexOrientation::Pointy, Point::new(0.0, 10.0)),
+            (HexOrientation::Flat, Point::new(10.0, 0.0)),
+        ] {
+            let cell = HexGrid::new(10.0)
+                .with_center(5.0, 0.0)
+                .with_orientation(orientation)
+                .cell();
+            assert_eq!(cell.edges.len(), 6);
+            assert!(cell
+                .edges
+                .iter()
+                .all(|edge| (edge.length() - 10.0).abs() < 1e-9));
+            let vertices = vertices(&cell);
+            assert_eq!(vertices.len(), 6);
+            let corner = corner + Point::new(5.0, 0.0);
+            assert!(vertices.iter().any(|p| p.distance(corner) < 1e-9));
+        }
+    }
+
+    #[test]
+    fn hexagons_share_edges_with_their_neighbors() {
+        for orientation in [HexOrientation::Pointy, HexOrientation::Flat] {
+            let cell = HexGrid::new(10.0).with_orientation(orientation).cell();
+            for shift in [cell.a, cell.b, cell.a - cell.b] {
+                assert!((shift.length() - 3f64.sqrt() * 10.0).abs() < 1e-9);
+                let shared = shared(&vertices(&cell), &shifted(&cell, shift));
+                assert_eq!(shared.len(), 2);
+                assert!((shared[0].distance(shared[1]) - 10.0).abs() < 1e-9);
+            }
+        }
+    }
+
+    #[test]
+    fn rhombille_divides_hexagons_into_rhombi() {
+        let cell = HexGrid::new(10.0)
+            .with_center(5.0, 5.0)
+            .with_tiling(HexTiling::Rhombille)
+            .cell();
+        assert_eq!(cell.edges.len(), 9);
+        let center = Point::new(5.0, 5.0);
+        let spokes = cell
+            .edges
+            .iter()
+            .filter(|edge| edge.start.distance(center) < 1e-9)
+            .collect::<Vec<_>>();
+        assert_eq!(spokes.len(), 3);
+        // every other vertex, 120° apart
+        for (i, p) in spokes.iter().enumerate() {
+            let q = spokes[(i + 1) % 3];
+            let (u, v) = (p.end - center, q.end - center);
+            assert!((u.length() - 10.0).abs() < 1e-9);
+            assert!((u.dot(v) / 100.0 + 0.5).abs() < 1e-9);
+        }
+        assert_eq!(vertices(&cell).len(), 7);
+    }
+
+    #[test]
+    fn trihexagonal_hexagons_meet_at_vertices() {
+        for orientation in [HexOrientation::Pointy, HexOrientation::Flat] {
+            let cell = HexGrid::new(10.0)
+                .with_orientation(orientation)
+                .with_tiling(HexTiling::Trihexagonal)
+                .cell();
+            assert_eq!(cell.edges.len(), 6);
+            let hexagon = vertices(&cell);
+            let (a, b) = (shifted(&cell, cell.a), shifted(&cell, cell.b));
+            let corners = [shared(&hexagon, &a), shared(&hexagon, &b), shared(&a, &b)];
+            assert!(corners.iter().all(|corner| corner.len() == 1));
+            // the three hexagons leave a triangle with sides as long as theirs between them
+            for (i, corner) in corners.iter().enumerate() {
+                let next = corners[(i + 1) % 3][0];
+                assert!((corner[0].distance(next) - 10.0).abs() < 1e-9);
+            }
+        }
+    }
+}
@@ -0,0 +1,495 @@
+use std::{fmt, vec};
+
+use serde::{
+    de::{
+        self,
+        value::{MapAccessDeserializer, SeqAccessDeserializer},
+        DeserializeSeed, IntoDeserializer, MapAccess, SeqAccess, Visitor,
+    },
+    Deserialize, Deserializer, Serialize,
+};
+use serde_yaml::Value;
+
+use crate::{
+    error::{ErrorKind, GridError},
+    render::Style,
+    units::{Conversion, Length, ResolveUnits},
+    DeBruijnTiling, Grid, HexGrid, PeriodicGrid, PolarGrid,
+};
+
+/// One of the grids of a `GridCollection`. In YAML, the kind is chosen by a `type` field, which
+/// defaults to `lines`.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(tag = "type", rename_all = "kebab-case")]
+pub enum GridKind {
+    /// Parallel straight lines
+    Lines(Grid),
+    /// Concentric circles and radial spokes
+    Polar(PolarGrid),
+    /// Hexagons, or tilings derived from them
+    Hex(HexGrid),
+    /// Any periodic tiling
+    Periodic(PeriodicGrid),
+    /// The quasiperiodic tiling dual to other grids of the collection
+    DeBruijn(DeBruijnTiling),
+}
+
+/// Values of the `type` field.
+#[derive(Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+enum Kind {
+    Lines,
+    Polar,
+    Hex,
+    Periodic,
+    DeBruijn,
+}
+
+impl GridKind {
+    pub fn name(&self) -> Option<&str> {
+        match self {
+            GridKind::Lines(grid) => grid.name.as_deref(),
+            GridKind::Polar(grid) => grid.name.as_deref(),
+            GridKind::Hex(grid) => grid.name.as_deref(),
+            GridKind::Periodic(grid) => grid.name.as_deref(),
+            GridKind::DeBruijn(grid) => grid.name.as_deref(),
+        }
+    }
+
+    /// The grid's own style. Major lines may override it.
+    pub fn style(&self) -> Style<'_> {
+        let (stroke, stroke_width) = match self {
+            GridKind::Lines(grid) => (&grid.stroke, grid.stroke_width),
+            GridKind::Polar(grid) => (&grid.stroke, grid.stroke_width),
+            GridKind::Hex(grid) => (&grid.stroke, grid.stroke_width),
+            GridKind::Periodic(grid) => (&grid.stroke, grid.stroke_width),
+            GridKind::DeBruijn(grid) => (&grid.stroke, grid.stroke_width),
+        };
+        Style {
+            stroke: stroke.as_deref(),
+            stroke_width: stroke_width.map(Length::get),
+        }
+    }
+
+    /// The grid, if it is made of parallel lines.
+    pub fn as_lines(&self) -> Option<&Grid> {
+        match self {
+            GridKind::Lines(grid) => Some(grid),
+            _ => None,
+        }
+    }
+
+    /// Checks the grid, which belongs to a collection with the given grids.
+    pub(crate) fn validate(&self, grids: &[GridKind]) -> Result<(), GridError> {
+        match self {
+            GridKind::Lines(grid) => grid.validate(),
+            GridKind::Polar(grid) => grid.validate(),
+            GridKind::Hex(grid) => grid.validate(),
+            GridKind::Periodic(grid) => grid.validate(),
+            GridKind::DeBruijn(grid) => grid.validate(grids),
+        }
+    }
+}
+
+impl ResolveUnits for GridKind {
+    fn resolve_units(&mut self, units: &mut Conversion) {
+        match self {
+            GridKind::Lines(grid) => grid.resolve_units(units),
+            GridKind::Polar(grid) => grid.resolve_units(units),
+            GridKind::Hex(grid) => grid.resolve_units(units),
+            GridKind::Periodic(grid) => grid.resolve_units(units),
+            GridKind::DeBruijn(grid) => grid.resolve_units(units),
+        }
+    }
+}
+
+/// Returns the grids of parallel lines among those with the given indices, or among all `grids`
+/// if `indices` is `None`.
+pub(crate) fn line_grids<'a>(indices: Option<&[usize]>, grids: &'a [GridKind]) -> Vec<&'a Grid> {
+    match indices {
+        Some(indices) => indices
+            .iter()
+            .filter_map(|&i| grids[i].as_lines())
+            .collect(),
+        None => grids.iter().filter_map(GridKind::as_lines).collect(),
+    }
+}
+
+/// Checks that `indices`, the value of `field`, refer to grids of parallel lines.
+pub(crate) fn validate_line_grids(
+    field: &str,
+    indices: &[usize],
+    grids: &[GridKind],
+) -> Result<(), GridError> {
+    for (i, &grid) in indices.iter().enumerate() {
+        let kind = match grids.get(grid) {
+            None => ErrorKind::NoSuchGrid(grid),
+            Some(GridKind::Lines(_)) => continue,
+            Some(_) => ErrorKind::NotLines(grid),
+        };
+        return Err(GridError::new(format!("{}.{}", field, i), kind));
+    }
+    Ok(())
+}
+
+impl From<Grid> for GridKind {
+    fn from(grid: Grid) -> Self {
+        GridKind::Lines(grid)
+    }
+}
+
+impl From<PolarGrid> for GridKind {
+    fn from(grid: PolarGrid) -> Self {
+        GridKind::Polar(grid)
+    }
+}
+
+impl From<HexGrid> for GridKind {
+    fn from(grid: HexGrid) -> Self {
+        GridKind::Hex(grid)
+    }
+}
+
+impl From<PeriodicGrid> for GridKind {
+    fn from(grid: PeriodicGrid) -> Self {
+        GridKind::Periodic(grid)
+    }
+}
+
+impl From<DeBruijnTiling> for GridKind {
+    fn from(grid: DeBruijnTiling) -> Self {
+        GridKind::DeBruijn(grid)
+    }
+}
+
+impl<'de> Deserialize<'de> for GridKind {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_map(GridKindVisitor)
+    }
+}
+
+/// Picks the kind of grid from its `type` field. serde's internally tagged enums buffer all the
+/// fields while looking for the tag, which loses the positions of errors in them. Instead, the
+/// fields are read as lines, the default, until `type` names another kind; only the fields before
+/// it are buffered, to be read again as that kind.
+struct GridKindVisitor;
+
+impl<'de> Visitor<'de> for GridKindVisitor {
+    type Value = GridKind;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a grid")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<GridKind, A::Error> {
+        let mut fields = Fields::new(&mut map);
+        let err = match Grid::deserialize(MapAccessDeserializer::new(&mut fields)) {
+            Ok(grid) => return Ok(GridKind::Lines(grid)),
+            Err(err) => err,
+        };
+        // the fields may have failed as lines only because they are of another kind
+        let kind = match fields.kind {
+            Some(Kind::Lines) => return Err(err),
+            Some(kind) => kind,
+            None => match fields.find_kind()? {
+                Some(kind) => kind,
+                None => return Err(err),
+            },
+        };
+        let fields = MapAccessDeserializer::new(fields.replay());
+        match kind {
+            Kind::Lines => Grid::deserialize(fields).map(GridKind::Lines),
+            Kind::Polar => PolarGrid::deserialize(fields).map(GridKind::Polar),
+            Kind::Hex => HexGrid::deserialize(fields).map(GridKind::Hex),
+            Kind::Periodic => PeriodicGrid::deserialize(fields).map(GridKind::Periodic),
+            Kind::DeBruijn => DeBruijnTiling::deserialize(fields).map(GridKind::DeBruijn),
+        }
+    }
+}
+
+/// The fields of a grid other than `type`, read from `map`. Until `type` has been read, the
+/// fields are kept as they are read.
+struct Fields<'a, A> {
+    map: &'a mut A,
+    /// The kind of grid, once `type` has been read
+    kind: Option<Kind>,
+    /// The fields read so far, while they are being kept
+    kept: Option<Vec<(String, Value)>>,
+    /// Name of the field last read from `map`, until its value is read
+    key: Option<String>,
+    /// Kept fields to read again before the rest of `map`
+    replay: vec::IntoIter<(String, Value)>,
+    /// Value of the field last read from `replay`
+    replayed: Option<Value>,
+}
+
+impl<'a, 'de, A: MapAccess<'de>> Fields<'a, A> {
+    fn new(map: &'a mut A) -> Self {
+        Fields {
+            map,
+            kind: None,
+            kept: Some(Vec::new()),
+            key: None,
+            replay: Vec::new().into_iter(),
+            replayed: None,
+        }
+    }
+
+    /// Reads the rest of the fields while looking for `type`, after they failed to be read as
+    /// lines. Returns the kind it names, unless it is lines, leaving the fields after it unread.
+    ///
+    /// Only an unknown field is worth looking further for: the kinds agree on the fields they
+    /// share, so a bad value is an error whatever the kind.
+    fn find_kind(&mut self) -> Result<Option<Kind>, A::Error> {
+        let (kept, key) = match (&mut self.kept, self.key.take()) {
+            (Some(kept), Some(key)) => (kept, key),
+            _ => return Ok(None),
+        };
+        kept.push((key, self.map.next_value()?));
+        while let Some(key) = self.map.next_key::<String>()? {
+            if key == "type" {
+                let kind = self.map.next_value()?;
+                self.kind = Some(kind);
+                return Ok(Some(kind).filter(|&kind| kind != Kind::Lines));
+            }
+            kept.push((key, self.map.next_value()?));
+        }
+        Ok(None)
+    }
+
+    /// Returns the fields to be read from the start again, as the kind named by `type`.
+    fn replay(mut self) -> Self {
+        self.replay = self.kept.take().unwrap_or_default().into_iter();
+        self
+    }
+}
+
+impl<'de, A: MapAccess<'de>> MapAccess<'de> for Fields<'_, A> {
+    type Error = A::Error;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(
+        &mut self,
+        seed: K,
+    ) -> Result<Option<K::Value>, A::Error> {
+        if let Some((key, value)) = self.replay.next() {
+            self.replayed = Some(value);
+            return seed.deserialize(key.into_deserializer()).map(Some);
+        }
+        let mut seed = Some(seed);
+        loop {
+            let key = self.map.next_key_seed(FieldName {
+                seed: &mut seed,
+                name: &mut self.key,
+                type_read: self.kind.is_some(),
+            })?;
+            match key {
+                None => return Ok(None),
+                Some(Key::Field(key)) => return Ok(Some(key)),
+                Some(Key::Type) => {
+                    let kind = self.map.next_value()?;
+                    self.kind = Some(kind);
+                    if kind != Kind::Lines {
+                        // stop reading the fields as lines, to read them again as `kind`
+                        return Err(de::Error::custom("the grid is not made of lines"));
+                    }
+                    self.kept = None;
+                }
+            }
+        }
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(&mut self, seed: V) -> Result<V::Value, A::Error> {
+        if let Some(value) = self.replayed.take() {
+            return seed.deserialize(value).map_err(de::Error::custom);
+        }
+        let key = self.key.take().unwrap_or_default();
+        match &mut self.kept {
+            Some(kept) => self.map.next_value_seed(Keep { seed, key, kept }),
+            None => self.map.next_value_seed(seed),
+        }
+    }
+}
+
+/// A field name read by `FieldName`.
+enum Key<K> {
+    Type,
+    Field(K),
+}
+
+/// Reads the name of a field into `name`, and deserializes it with `seed` unless it is `type`.
+/// Once `type_read`, another `type` is an error.
+struct FieldName<'a, K> {
+    seed: &'a mut Option<K>,
+    name: &'a mut Option<String>,
+    type_read: bool,
+}
+
+impl<'de, K: DeserializeSeed<'de>> DeserializeSeed<'de> for FieldName<'_, K> {
+    type Value = Key<K::Value>;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
+        deserializer.deserialize_str(self)
+    }
+}
+
+impl<'de, K: DeserializeSeed<'de>> Visitor<'de> for FieldName<'_, K> {
+    type Value = Key<K::Value>;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a field name")
+    }
+
+    fn visit_str<E: de::Error>(self, name: &str) -> Result<Self::Value, E> {
+        if name == "type" {
+            return match self.type_read {
+                true => Err(E::duplicate_field("type")),
+                false => Ok(Key::Type),
+            };
+        }
+        *self.name = Some(name.to_owned());
+        let seed = self.seed.take().expect("field name read twice");
+        seed.deserialize(name.into_deserializer()).map(Key::Field)
+    }
+}
+
+/// Deserializes the value of field `key` with `seed`, and adds it to `kept`. The value is read
+/// into a `Value` by a visitor, so that errors deserializing it are reported at its position.
+struct Keep<'a, V> {
+    seed: V,
+    key: String,
+    kept: &'a mut Vec<(String, Value)>,
+}
+
+impl<V> Keep<'_, V> {
+    fn keep<'de, E: de::Error>(self, value: Value) -> Result<V::Value, E>
+    where
+        V: DeserializeSeed<'de>,
+    {
+        let result = self.seed.deserialize(value.clone());
+        self.kept.push((self.key, value));
+        result.map_err(de::Error::custom)
+    }
+}
+
+impl<'de, V: DeserializeSeed<'de>> DeserializeSeed<'de> for Keep<'_, V> {
+    type Value = V::Value;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<V::Value, D::Error> {
+        deserializer.deserialize_any(self)
+    }
+}
+
+impl<'de, V: DeserializeSeed<'de>> Visitor<'de> for Keep<'_, V> {
+    type Value = V::Value;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("any value")
+    }
+
+    fn visit_bool<E: de::Error>(self, value: bool) -> Result<V::Value, E> {
+        self.keep(value.into())
+    }
+
+    fn visit_i64<E: de::Error>(self, value: i64) -> Result<V::Value, E> {
+        self.keep(value.into())
+    }
+
+    fn visit_u64<E: de::Error>(self, value: u64) -> Result<V::Value, E> {
+        self.keep(value.into())
+    }
+
+    fn visit_f64<E: de::Error>(self, value: f64) -> Result<V::Value, E> {
+        self.keep(value.into())
+    }
+
+    fn visit_str<E: de::Error>(self, value: &str) -> Result<V::Value, E> {
+        self.keep(value.into())
+    }
+
+    fn visit_unit<E: de::Error>(self) -> Result<V::Value, E> {
+        self.keep(Value::Null)
+    }
+
+    fn visit_seq<S: SeqAccess<'de>>(self, seq: S) -> Result<V::Value, S::Error> {
+        let value = Value::deserialize(SeqAccessDeserializer::new(seq))?;
+        self.keep(value)
+    }
+
+    fn visit_map<M: MapAccess<'de>>(self, map: M) -> Result<V::Value, M::Error> {
+        let value = Value::deserialize(MapAccessDeserializer::new(map))?;
+        self.keep(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::GridCollection;
+
+    fn error(source: &str) -> String {
+        serde_yaml::from_str::<GridCollection>(source)
+            .unwrap_err()
+            .to_string()
+    }
+
+    #[test]
+    fn errors_point_into_the_grid() {
+        let source = "grids:\n  - step: 10\n  - step: abc\n";
+        assert!(error(source).starts_with("grids[1].step: invalid value"));
+        assert!(error(source).ends_with("at line 3 column 11"));
+
+        let source = "grids:\n  - type: polar\n    radial-step: 1\n    radius: 2\n";
+        assert!(error(source).starts_with("grids[0]: unknown field `radius`"));
+        assert!(error(source).ends_with("at line 4 column 5"));
+    }
+
+    #[test]
+    fn type_goes_anywhere() {
+        let grids = |source| {
+            serde_yaml::from_str::<GridCollection>(source)
+                .unwrap()
+                .grids
+        };
+        let first = grids("grids:\n  - type: polar\n    radial-step: 5\n    stroke: red\n");
+        let later = grids("grids:\n  - radial-step: 5\n    type: polar\n    stroke: red\n");
+        let last = grids("grids:\n  - stroke: red\n    radial-step: 5\n    type: polar\n");
+        assert!(matches!(first[0], GridKind::Polar(_)));
+        assert_eq!(later, first);
+        assert_eq!(last, first);
+        let lines = grids("grids:\n  - step: 10\n    type: lines\n    theta: 90\n");
+        assert_eq!(lines, grids("grids:\n  - {step: 10, theta: 90}\n"));
+    }
+
+    #[test]
+    fn errors_keep_their_location_around_type() {
+        // after `type`, and in a grid of lines before it
+        let source = "grids:\n  - radial-step: 5\n    type: polar\n    stroke-width: abc\n";
+        assert!(error(source).starts_with("grids[0].stroke-width: invalid value"));
+        assert!(error(source).ends_with("at line 4 column 19"));
+        let source = "grids:\n  - step: abc\n    type: lines\n";
+        assert!(error(source).starts_with("grids[0].step: invalid value"));
+        assert!(error(source).ends_with("at line 2 column 11"));
+        // values kept whole point at their start
+        let source = "grids:\n  - step: 10\n    major: [{every: 5}, {every: x}]\n    type: polar\n";
+        assert!(error(source).starts_with("grids[0].major: invalid type"));
+        assert!(error(source).ends_with("at line 3 column 12"));
+        // fields of the wrong kind
+        let source = "grids:\n  - radial-step: 5\n    type: polar\n    step: 10\n";
+        assert!(error(source).starts_with("grids[0]: unknown field `step`"));
+        let source = "grids:\n  - radial-step: 5\n";
+        assert!(error(source).contains("unknown field `radial-step`"));
+    }
+
+    #[test]
+    fn rejects_unknown_types_and_fields() {
+        let source = "grids:\n  - type: square\n";
+        assert!(error(source).starts_with("grids[0].type: unknown variant `square`"));
+        let source = "grids:\n  - step: 10\n    type: square\n";
+        assert!(error(source).starts_with("grids[0].type: unknown variant `square`"));
+        let source = "grids:\n  - type: polar\n    radial-step: 5\n    type: hex\n";
+        assert!(error(source).contains("duplicate field `type`"));
+        let source = "grids:\n  - step: 10\n    stroke-widht: 2\n";
+        assert!(error(source).starts_with("grids[0]: unknown field `stroke-widht`"));
+        assert!(error(source).ends_with("at line 3 column 5"));
+    }
+}
@@ -0,0 +1,836 @@
+use std::borrow::Cow;
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+mod arrangement;
+mod color;
+mod dedup;
+pub mod error;
+mod fill;
+pub mod geometry;
+mod grid;
+mod hex;
+mod kind;
+mod markers;
+mod multigrid;
+pub mod optimize;
+mod page;
+mod polar;
+mod rect;
+mod region;
+pub mod render;
+mod tiling;
+mod units;
+
+pub use arrangement::Face;
+pub use color::Color;
+pub use dedup::{Deduplicate, Precedence};
+pub use error::{GridError, LoadError};
+pub use fill::{Fill, IndexColor};
+pub use geometry::{Arc, Point, Segment};
+pub use grid::{Align, Grid, MajorLines};
+pub use hex::{HexGrid, HexOrientation, HexTiling};
+pub use kind::GridKind;
+pub use markers::{MarkerShape, Markers};
+pub use multigrid::DeBruijnTiling;
+pub use page::{Margins, Orientation, Page, PageSize};
+pub use polar::PolarGrid;
+pub use rect::Rect;
+pub use region::{Circle, Ellipse, Region, Shape};
+pub use render::{
+    DxfRenderer, GroupInfo, GroupKind, PdfRenderer, PlotterRenderer, PlotterSettings, PngRenderer,
+    Renderer, Resolution, Style, SvgRenderer,
+};
+pub use tiling::PeriodicGrid;
+pub use units::{Length, ParseLengthError, Unit};
+
+use error::{color, non_negative, ErrorKind};
+use units::{Conversion, ResolveUnits};
+
+/// A set of grids and the document they are drawn in. Lengths with a unit of their own are
+/// converted to `units` once the collection is deserialized; see `resolve_units`.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(remote = "Self")]
+pub struct GridCollection {
+    /// Unit of lengths given as plain numbers, which other lengths such as `0.25in` are converted
+    /// to. If set, the rendered document has a matching physical size. Defaults to px once the
+    /// collection is read if it has a page or lengths with units.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub units: Option<Unit>,
+    /// Page which the bounds and clip are derived from, unless they are given
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub page: Option<Page>,
+    /// Bounds for the generated SVG, or the whole page if unset
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bounds: Option<Rect<Length>>,
+    /// Area of the image which will be rendered, or the area inside the margins of the page if
+    /// unset. May be given as a rectangle, or as a region such as a polygon or circle with holes.
+    #[serde(
+        default,
+        with = "region::rect_or_region",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub clip: Option<Region>,
+    /// Default stroke color
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stroke: Option<String>,
+    /// Default stroke width
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stroke_width: Option<Length>,
+    /// Whether to emit an SVG clip-path for the viewable area, in addition to clipping the lines
+    /// themselves
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub clip_path: bool,
+    /// If set, lines shared by several grids are only drawn by one of them
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub deduplicate: Option<Deduplicate>,
+    /// Settings for HPGL and G-code output
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub plotter: Option<PlotterSettings>,
+    /// Grids to draw, in order from bottom to top
+    #[serde(default)]
+    pub grids: Vec<GridKind>,
+    /// How to fill the faces formed by the lines of the grids, behind all grids
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fill: Option<Fill>,
+    /// Marks to draw where grids cross, on top of all grids. May be given as a single set of
+    /// markers or a list.
+    #[serde(
+        default,
+        deserialize_with = "grid::one_or_many",
+        skip_serializing_if = "Vec::is_empty"
+    )]
+    pub markers: Vec<Markers>,
+}
+
+impl<'de> Deserialize<'de> for GridCollection {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let mut collection = GridCollection::deserialize(deserializer)?;
+        collection.resolve_units();
+        Ok(collection)
+    }
+}
+
+impl Serialize for GridCollection {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        GridCollection::serialize(self, serializer)
+    }
+}
+
+impl GridCollection {
+    /// Creates an empty collection with the given bounds.
+    pub fn new(bounds: Rect) -> Self {
+        GridCollection {
+            bounds: Some(bounds.into()),
+            ..Default::default()
+        }
+    }
+
+    /// Creates an empty collection with bounds and clip derived from `page`.
+    pub fn on_page(page: Page) -> Self {
+        GridCollection {
+            page: Some(page),
+            ..Default::default()
+        }
+    }
+
+    /// Sets the unit of all lengths, giving the rendered document a physical size.
+    pub fn with_units(mut self, units: Unit) -> Self {
+        self.units = Some(units);
+        self
+    }
+
+    /// Sets the area of the image which will be rendered, which may be a `Rect` or a `Region`.
+    pub fn with_clip(mut self, clip: impl Into<Region>) -> Self {
+        self.clip = Some(clip.into());
+        self
+    }
+
+    /// Sets the default stroke color.
+    pub fn with_stroke(mut self, stroke: impl Into<String>) -> Self {
+        self.stroke = Some(stroke.into());
+        self
+    }
+
+    /// Sets the default stroke width.
+    pub fn with_stroke_width(mut self, stroke_width: f64) -> Self {
+        self.stroke_width = Some(stroke_width.into());
+        self
+    }
+
+    /// Sets whether to emit an SVG clip-path for the viewable area.
+    pub fn with_clip_path(mut self, clip_path: bool) -> Self {
+        self.clip_path = clip_path;
+        self
+    }
+
+    /// Draws lines shared by several grids only once.
+    pub fn with_deduplicate(mut self, deduplicate: Deduplicate) -> Self {
+        self.deduplicate = Some(deduplicate);
+        self
+    }
+
+    /// Adds a grid of any kind on top of the existing ones.
+    pub fn with_grid(mut self, grid: impl Into<GridKind>) -> Self {
+        self.grids.push(grid.into());
+        self
+    }
+
+    /// Fills the faces formed by the lines of the grids.
+    pub fn with_fill(mut self, fill: Fill) -> Self {
+        self.fill = Some(fill);
+        self
+    }
+
+    /// Adds a set of markers on top of the existing ones.
+    pub fn with_markers(mut self, markers: Markers) -> Self {
+        self.markers.push(markers);
+        self
+    }
+
+    /// Parses a collection from YAML, and checks that it is valid. Validation errors are located
+    /// in `source`.
+    pub fn from_yaml(source: &str) -> Result<Self, LoadError> {
+        let collection: GridCollection = serde_yaml::from_str(source)?;
+        collection.validate().map_err(|err| err.locate(source))?;
+        Ok(collection)
+    }
+
+    /// Converts all lengths with a unit of their own to `units`. If it is unset, they are converted
+    /// to px, and `units` is set to px if there were any, or a page, so that the document still
+    /// gets a physical size. This is done when a collection is deserialized, and only needs
+    /// calling again after changing `units` or adding lengths with units.
+    pub fn resolve_units(&mut self) {
+        let units = &mut Conversion {
+            unit: self.units.unwrap_or_default(),
+            found_unit: false,
+        };
+        self.page.resolve_units(units);
+        self.bounds.resolve_units(units);
+        self.clip.resolve_units(units);
+        self.stroke_width.resolve_units(units);
+        self.deduplicate.resolve_units(units);
+        self.grids.resolve_units(units);
+        self.markers.resolve_units(units);
+        if units.found_unit || self.page.is_some() {
+            self.units.get_or_insert(units.unit);
+        }
+    }
+
+    /// Checks that all values in the collection and its grids are usable.
+    pub fn validate(&self) -> Result<(), GridError> {
+        if let Some(page) = &self.page {
+            page.validate("page", self.units.unwrap_or_default())?;
+        }
+        match &self.bounds {
+            Some(bounds) => bounds.get().validate("bounds")?,
+            None if self.page.is_none() => {
+                return Err(GridError::new("bounds", ErrorKind::MissingBounds))
+            }
+            None => {}
+        }
+        if let Some(clip) = &self.clip {
+            match clip.as_rect() {
+                Some(rect) => rect.validate("clip")?,
+                None => clip.validate("clip")?,
+            }
+        }
+        if !self.bounds().contains(&self.viewable_area()) {
+            let field = if self.clip.is_some() { "clip" } else { "page" };
+            return Err(GridError::new(field, ErrorKind::ClipOutsideBounds));
+        }
+        if let Some(stroke) = &self.stroke {
+            color(stroke).map_err(|kind| GridError::new("stroke", kind))?;
+        }
+        if let Some(width) = self.stroke_width {
+            non_negative(width.get()).map_err(|kind| GridError::new("stroke_width", kind))?;
+        }
+        if let Some(deduplicate) = &self.deduplicate {
+            deduplicate.validate("deduplicate")?;
+        }
+        if let Some(plotter) = &self.plotter {
+            plotter.validate("plotter")?;
+        }
+        for (i, grid) in self.grids.iter().enumerate() {
+            grid.validate(&self.grids).map_err(|err| err.in_grid(i))?;
+        }
+        if let Some(fill) = &self.fill {
+            fill.validate("fill", line_grids(&self.grids).count())?;
+        }
+        for (i, markers) in self.markers.iter().enumerate() {
+            markers.validate(&format!("markers.{}", i), &self.grids)?;
+        }
+        Ok(())
+    }
+
+    /// The bounds of the document, taken from the page if they aren't given.
+    pub fn bounds(&self) -> Rect {
+        let page = self
+            .page
+            .map(|page| page.bounds(self.units.unwrap_or_default()));
+        self.bounds.map(Rect::get).or(page).unwrap_or_default()
+    }
+
+    /// The area of the image which will be rendered, or the rectangle around it if the clip isn't
+    /// a rectangle.
+    pub fn viewable_area(&self) -> Rect {
+        self.viewable_region().shape.bounding_box()
+    }
+
+    /// The area of the image which will be rendered, as a region.
+    pub fn viewable_region(&self) -> Region {
+        let page = self
+            .page
+            .map(|page| page.inside(self.units.unwrap_or_default()));
+        match &self.clip {
+            Some(clip) => clip.clone(),
+            None => page.unwrap_or_else(|| self.bounds()).into(),
+        }
+    }
+
+    /// The clip, if lines must be clipped to it beyond the rectangle of the viewable area.
+    fn clip_region(&self) -> Option<&Region> {
+        self.clip.as_ref().filter(|clip| clip.as_rect().is_none())
+    }
+
+    /// The grids, with those which are aligned to the viewable area moved into place; see
+    /// `Grid::aligned`.
+    fn aligned_grids(&self) -> Cow<'_, [GridKind]> {
+        let aligned = |grid: &GridKind| grid.as_lines().is_some_and(|grid| grid.align.is_some());
+        if !self.grids.iter().any(aligned) {
+            return Cow::Borrowed(&self.grids);
+        }
+        let area = self.viewable_area();
+        let grids = self.grids.iter().map(|grid| match grid {
+            GridKind::Lines(lines) => GridKind::Lines(lines.aligned(area)),
+            _ => grid.clone(),
+        });
+        Cow::Owned(grids.collect())
+    }
+
+    /// Returns the line segments of all grids of parallel lines which aren't hidden, clipped to
+    /// the viewable area, from the bottom grid to the top. The collection must be valid; see
+    /// `validate`.
+    pub fn segments(&self) -> impl Iterator<Item = GridSegment> + '_ {
+        let area = self.viewable_area();
+        let grids = self.aligned_grids();
+        let visible = line_grids(&grids).filter(|(_, grid)| !grid.hidden);
+        let segments = visible.flat_map(|(i, grid)| {
+            self.grid_lines(&grids, i, grid, area)
+                .map(move |(index, segment)| GridSegment {
+                    grid: i,
+                    index,
+                    segment,
+                })
+        });
+        segments.collect::<Vec<_>>().into_iter()
+    }
+
+    /// Returns the faces into which the lines of all grids of parallel lines, including hidden
+    /// ones, divide the viewable area. Each face records which lines of each grid it lies between.
+    /// If the clip isn't a rectangle, faces crossing its edge are cut into pieces inside it; see
+    /// `Face`. The collection must be valid; see `validate`.
+    pub fn faces(&self) -> Vec<Face> {
+        let grids = self.aligned_grids();
+        let grids = line_grids(&grids).map(|(_, grid)| grid).collect::<Vec<_>>();
+        let faces = arrangement::faces(&grids, self.viewable_area());
+        match self.clip_region() {
+            Some(clip) => arrangement::clip_faces(faces, clip),
+            None => faces,
+        }
+    }
+
+    /// Returns the lines of `grid`, which is number `i` of `grids`, within `area`, leaving out
+    /// those which another grid draws instead.
+    fn grid_lines<'a>(
+        &'a self,
+        grids: &'a [GridKind],
+        i: usize,
+        grid: &'a Grid,
+        area: Rect,
+    ) -> impl Iterator<Item = (i64, Segment)> + 'a {
+        let clip = self.clip_region();
+        grid.segments(area)
+            .filter(move |&(index, _)| self.is_drawn(grids, i, grid, index))
+            .flat_map(move |(index, segment)| {
+                clip_segment(clip, segment)
+                    .into_iter()
+                    .map(move |segment| (index, segment))
+            })
+    }
+
+    /// Whether line `index` of `grid`, which is number `i` of `grids`, is drawn by that grid,
+    /// rather than by another grid with the same line, according to the deduplication settings.
+    fn is_drawn(&self, grids: &[GridKind], i: usize, grid: &Grid, index: i64) -> bool {
+        let deduplicate = match &self.deduplicate {
+            Some(deduplicate) => deduplicate,
+            None => return true,
+        };
+        let width = |grid: &Grid| {
+            grid.stroke_width
+                .or(self.stroke_width)
+                .map_or(1.0, Length::get)
+        };
+        let this = (i, width(grid));
+        let (theta, offset) = grid.line_position(index);
+        !line_grids(grids).any(|(j, other)| {
+            j != i
+                && !other.hidden
+                // a grid with a region may only have part of the line
+                && other.region.is_none()
+                && deduplicate.keep.prefers((j, width(other)), this)
+                && other.has_line(
+                    theta,
+                    offset,
+                    deduplicate.tolerance.get(),
+                    deduplicate.angle_tolerance,
+                )
+        })
+    }
+
+    /// Renders the collection with the given renderer.
+    pub fn render<R: Renderer + ?Sized>(&self, renderer: &mut R) -> Result<(), GridError> {
+        self.validate()?;
+        let style = Style {
+            stroke: Some(self.stroke.as_deref().unwrap_or("black")),
+            stroke_width: self.stroke_width.map(Length::get),
+        };
+        let area = self.viewable_area();
+        let clip = self.clip_region();
+        let grids = self.aligned_grids();
+        // a page always gives the document a physical size
+        let units = self
+            .units
+            .or_else(|| self.page.as_ref().map(|_| Unit::default()));
+        renderer.begin_document(self.bounds(), &self.viewable_region(), units, &style);
+        if let Some(fill) = &self.fill {
+            renderer.begin_group(&GroupInfo {
+                kind: GroupKind::Fill,
+                name: None,
+                style: Style::default(),
+            });
+            for face in self.faces() {
+                if let Some(color) = fill.color(&face.indices) {
+                    renderer.fill(&face.points, color);
+                }
+            }
+            renderer.end_group();
+        }
+        for (i, grid) in grids.iter().enumerate() {
+            match grid {
+                GridKind::Lines(lines) => self.render_lines(renderer, &grids, i, lines, area),
+                GridKind::Polar(polar) => render_polar(renderer, i, polar, area, clip),
+                GridKind::Hex(hex) => render_segments(renderer, i, grid, &hex.segments(area), clip),
+                GridKind::Periodic(tiling) => {
+                    render_segments(renderer, i, grid, &tiling.segments(area), clip)
+                }
+                GridKind::DeBruijn(tiling) => {
+                    render_polygons(renderer, i, grid, &tiling.tiles(&grids, area), clip)
+                }
+            }
+        }
+        let region = self.viewable_region();
+        for (i, markers) in self.markers.iter().enumerate() {
+            renderer.begin_group(&GroupInfo {
+                kind: GroupKind::Markers(i),
+                name: markers.name.as_deref(),
+                style: Style {
+                    stroke: markers.stroke.as_deref(),
+                    stroke_width: markers.stroke_width.map(Length::get),
+                },
+            });
+            let points = markers.points(&grids, area).into_iter();
+            for point in points.filter(|&point| clip.is_none_or(|clip| clip.contains(point))) {
+                if markers.shape == MarkerShape::Dot {
+                    // dots are only drawn whole
+                    let dot = Arc::circle(point, markers.size.get() / 2.0);
+                    if region.clip_arc(dot) == [dot] {
+                        renderer.dot(point, dot.radius);
+                    }
+                } else {
+                    for stroke in markers.strokes(point, area) {
+                        for stroke in clip_segment(clip, stroke) {
+                            renderer.segment(&stroke);
+                        }
+                    }
+                }
+            }
+            renderer.end_group();
+        }
+        renderer.end_document();
+        Ok(())
+    }
+
+    /// Renders a grid of parallel lines, which is number `i` of `grids`, as a group of regular
+    /// lines followed by a group for each tier of major lines.
+    fn render_lines<R: Renderer + ?Sized>(
+        &self,
+        renderer: &mut R,
+        grids: &[GridKind],
+        i: usize,
+        grid: &Grid,
+        area: Rect,
+    ) {
+        if grid.hidden {
+            return;
+        }
+        let mut groups = vec![Vec::new(); grid.major.len() + 1];
+        for (index, segment) in self.grid_lines(grids, i, grid, area) {
+            let group = grid.tier(index).map_or(0, |tier| tier + 1);
+            groups[group].push(segment);
+        }
+        let mut tiers = (0..grid.major.len()).collect::<Vec<_>>();
+        tiers.sort_by_key(|&tier| grid.major[tier].every);
+        let grid_style = Style {
+            stroke: grid.stroke.as_deref(),
+            stroke_width: grid.stroke_width.map(Length::get),
+        };
+        for tier in std::iter::once(None).chain(tiers.into_iter().map(Some)) {
+            let style = match tier {
+                Some(tier) => Style {
+                    stroke: grid.major[tier].stroke.as_deref(),
+                    stroke_width: grid.major[tier].stroke_width.map(Length::get),
+                }
+                .or(grid_style),
+                None => grid_style,
+            };
+            renderer.begin_group(&GroupInfo {
+                kind: GroupKind::Grid { grid: i, tier },
+                name: grid.name.as_deref(),
+                style,
+            });
+            for segment in &groups[tier.map_or(0, |tier| tier + 1)] {
+                renderer.segment(segment);
+            }
+            renderer.end_group();
+        }
+    }
+
+    /// Renders the collection as an SVG document.
+    pub fn to_svg(&self) -> Result<svg::Document, GridError> {
+        let mut renderer = SvgRenderer::new().with_clip_path(self.clip_path);
+        self.render(&mut renderer)?;
+        Ok(renderer.into_document())
+    }
+}
+
+/// Returns the grids made of parallel lines, with their indices in `grids`.
+fn line_grids(grids: &[GridKind]) -> impl Iterator<Item = (usize, &Grid)> {
+    grids
+        .iter()
+        .enumerate()
+        .filter_map(|(i, grid)| Some((i, grid.as_lines()?)))
+}
+
+/// Returns the parts of `segment` inside `clip`, or the whole segment if there is no clip.
+fn clip_segment(clip: Option<&Region>, segment: Segment) -> Vec<Segment> {
+    match clip {
+        Some(clip) => clip.clip_segment(segment),
+        None => vec![segment],
+    }
+}
+
+/// Renders a polar grid, which is number `i` in the collection, as a single group, clipped to
+/// `area` and `clip`.
+fn render_polar<R: Renderer + ?Sized>(
+    renderer: &mut R,
+    i: usize,
+    grid: &PolarGrid,
+    area: Rect,
+    clip: Option<&Region>,
+) {
+    renderer.begin_group(&GroupInfo {
+        kind: GroupKind::Grid {
+            grid: i,
+            tier: None,
+        },
+        name: grid.name.as_deref(),
+        style: Style {
+            stroke: grid.stroke.as_deref(),
+            stroke_width: grid.stroke_width.map(Length::get),
+        },
+    });
+    for (_, arc) in grid.circles(area) {
+        match clip {
+            Some(clip) => clip.clip_arc(arc).iter().for_each(|arc| renderer.arc(arc)),
+            None => renderer.arc(&arc),
+        }
+    }
+    for (_, spoke) in grid.spokes(area) {
+        for spoke in clip_segment(clip, spoke) {
+            renderer.segment(&spoke);
+        }
+    }
+    renderer.end_group();
+}
+
+/// Renders `segments` of `grid`, which is number `i` in the collection, as a single group,
+/// clipped to `clip`.
+fn render_segments<R: Renderer + ?Sized>(
+    renderer: &mut R,
+    i: usize,
+    grid: &GridKind,
+    segments: &[Segment],
+    clip: Option<&Region>,
+) {
+    renderer.begin_group(&GroupInfo {
+        kind: GroupKind::Grid {
+            grid: i,
+            tier: None,
+        },
+        name: grid.name(),
+        style: grid.style(),
+    });
+    for &segment in segments {
+        for segment in clip_segment(clip, segment) {
+            renderer.segment(&segment);
+        }
+    }
+    renderer.end_group();
+}
+
+/// Renders `polygons` of `grid`, which is number `i` in the collection, as a single group. If
+/// there is a clip, the polygons are clipped to it as separate edges.
+fn render_polygons<R: Renderer + ?Sized>(
+    renderer: &mut R,
+    i: usize,
+    grid: &GridKind,
+    polygons: &[Vec<Point>],
+    clip: Option<&Region>,
+) {
+    renderer.begin_group(&GroupInfo {
+        kind: GroupKind::Grid {
+            grid: i,
+            tier: None,
+        },
+        name: grid.name(),
+        style: grid.style(),
+    });
+    for polygon in polygons {
+        let clip = match clip {
+            Some(clip) => clip,
+            None => {
+                renderer.polygon(polygon);
+                continue;
+            }
+        };
+        for (j, &start) in polygon.iter().enumerate() {
+            let edge = Segment::new(start, polygon[(j + 1) % polygon.len()]);
+            for edge in clip.clip_segment(edge) {
+                renderer.segment(&edge);
+            }
+        }
+    }
+    renderer.end_group();
+}
+
+/// A line segment belonging to one of the grids of a `GridCollection`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct GridSegment {
+    /// Index of the grid in the collection
+    pub grid: usize,
+    /// Index of the line within the grid; see `Grid::lines`
+    pub index: i64,
+    pub segment: Segment,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SOURCE: &str = "
+units: mm
+bounds: {min-x: 0, max-x: 100, min-y: 0, max-y: 100}
+stroke_width: 0.5pt
+grids:
+  - step: 1in
+    cx: 10
+";
+
+    #[test]
+    fn deserializing_resolves_units() {
+        let collection: GridCollection = serde_yaml::from_str(SOURCE).unwrap();
+        let grid = collection.grids[0].as_lines().unwrap();
+        assert_eq!(grid.step, Length::new(25.4));
+        assert_eq!(grid.cx, Length::new(10.0));
+        let width = collection.stroke_width.unwrap();
+        assert_eq!(width.unit, None);
+        assert!((width.value - 0.5 * 25.4 / 72.0).abs() < 1e-12);
+
+        let loaded = GridCollection::from_yaml(SOURCE).unwrap();
+        assert_eq!(loaded.grids, collection.grids);
+    }
+
+    #[test]
+    fn resolving_units_again_keeps_lengths() {
+        let mut collection = GridCollection::from_yaml(SOURCE).unwrap();
+        let grids = collection.grids.clone();
+        collection.resolve_units();
+        assert_eq!(collection.grids, grids);
+    }
+
+    #[test]
+    fn documents_with_lengths_in_units_get_a_physical_size() {
+        let size = |source: &str| {
+            let collection = GridCollection::from_yaml(source).unwrap();
+            let svg = collection.to_svg().unwrap().to_string();
+            let attribute = |name: &str| {
+                let start = svg.find(&format!(" {}=\"", name))? + name.len() + 3;
+                let end = start + svg[start..].find('"')?;
+                Some(svg[start..end].to_string())
+            };
+            (collection.units, attribute("width"), attribute("height"))
+        };
+        let (units, width, height) = size("page: {size: a4, margin: 10mm}\ngrids: [{step: 5mm}]");
+        assert_eq!(units, Some(Unit::Px));
+        let width = width.unwrap();
+        let width = width.strip_suffix("px").unwrap().parse::<f64>().unwrap();
+        assert!((width - Unit::Mm.convert(210.0, Unit::Px)).abs() < 1e-9);
+        assert!(height.unwrap().ends_with("px"));
+        let (units, width, _) =
+            size("bounds: {min-x: 0, max-x: 100, min-y: 0, max-y: 100}\ngrids: [{step: 0.5in}]");
+        assert_eq!((units, width.as_deref()), (Some(Unit::Px), Some("100px")));
+        // plain numbers have no physical size
+        let (units, width, _) =
+            size("bounds: {min-x: 0, max-x: 100, min-y: 0, max-y: 100}\ngrids: [{step: 5}]");
+        assert_eq!((units, width), (None, None));
+    }
+
+    /// A group drawn by a `Recorder`.
+    struct Group {
+        kind: GroupKind,
+        stroke: Option<String>,
+        stroke_width: Option<f64>,
+        segments: Vec<Segment>,
+    }
+
+    /// Records the style and segments of each group.
+    #[derive(Default)]
+    struct Recorder {
+        groups: Vec<Group>,
+    }
+
+    impl Renderer for Recorder {
+        fn begin_document(&mut self, _: Rect, _: &Region, _: Option<Unit>, _: &Style) {}
+
+        fn begin_group(&mut self, group: &GroupInfo) {
+            self.groups.push(Group {
+                kind: group.kind,
+                stroke: group.style.stroke.map(String::from),
+                stroke_width: group.style.stroke_width,
+                segments: Vec::new(),
+            });
+        }
+
+        fn segment(&mut self, segment: &Segment) {
+            self.groups.last_mut().unwrap().segments.push(*segment);
+        }
+
+        fn arc(&mut self, _: &Arc) {}
+
+        fn polygon(&mut self, _: &[Point]) {}
+
+        fn dot(&mut self, _: Point, _: f64) {}
+
+        fn fill(&mut self, _: &[Point], _: &str) {}
+
+        fn end_group(&mut self) {}
+
+        fn end_document(&mut self) {}
+
+        fn write(&self, _: &mut dyn std::io::Write) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn major_lines_are_drawn_in_tiers_with_their_own_style() {
+        let grid = Grid::new(10.0)
+            .with_stroke("gray")
+            .with_stroke_width(0.5)
+            .with_major(MajorLines::new(10).with_stroke_width(2.0))
+            .with_major(MajorLines::new(5).with_stroke("red").with_stroke_width(1.0));
+        let collection = GridCollection::new(Rect::new(0.0, 100.0, 0.0, 100.0)).with_grid(grid);
+        let mut recorder = Recorder::default();
+        collection.render(&mut recorder).unwrap();
+        let xs = |segments: &[Segment]| segments.iter().map(|s| s.start.x).collect::<Vec<_>>();
+        // minor lines first, then the tiers from the closest spacing to the widest
+        let groups = recorder
+            .groups
+            .iter()
+            .map(|group| {
+                let stroke = group.stroke.as_deref();
+                (group.kind, stroke, group.stroke_width, xs(&group.segments))
+            })
+            .collect::<Vec<_>>();
+        let grid = |tier| GroupKind::Grid { grid: 0, tier };
+        assert_eq!(
+            groups,
+            vec![
+                (
+                    grid(None),
+                    Some("gray"),
+                    Some(0.5),
+                    vec![10.0, 20.0, 30.0, 40.0, 60.0, 70.0, 80.0, 90.0]
+                ),
+                (grid(Some(1)), Some("red"), Some(1.0), vec![50.0]),
+                (grid(Some(0)), Some("gray"), Some(2.0), vec![0.0, 100.0]),
+            ]
+        );
+    }
+
+    #[test]
+    fn dots_are_only_drawn_whole() {
+        let collection = GridCollection::new(Rect::new(0.0, 20.0, 0.0, 20.0))
+            .with_grid(Grid::new(10.0))
+            .with_grid(Grid::new(10.0).with_theta(90.0))
+            .with_markers(Markers::new(MarkerShape::Dot, 2.0));
+        let svg = collection.to_svg().unwrap().to_string();
+        // the dots on the edges would spill past the bounds
+        assert_eq!(svg.matches("<circle").count(), 1);
+        assert!(svg.contains("cx=\"10\" cy=\"10\""));
+    }
+
+    #[test]
+    fn deduplicating_keeps_lines_at_other_angles() {
+        let deduplicate = Deduplicate {
+            tolerance: Length::new(0.9),
+            ..Default::default()
+        };
+        let collection = GridCollection::new(Rect::new(0.0, 100.0, 0.0, 100.0))
+            .with_deduplicate(deduplicate)
+            .with_grid(Grid::new(10.0))
+            .with_grid(Grid::new(10.0).with_theta(0.8))
+            .with_grid(Grid::new(10.0).with_center(0.5, 0.0));
+        let grids = collection.segments().map(|segment| segment.grid);
+        let mut counts = [0; 3];
+        grids.for_each(|grid| counts[grid] += 1);
+        assert_eq!(counts[0], 0);
+        assert!(counts[1] > 0);
+        assert!(counts[2] > 0);
+
+        // lines 0.8° apart are parallel within an `angle_tolerance` of 1°, even though 0.8 is more
+        // than `tolerance`
+        let counts = |angle_tolerance| {
+            let deduplicate = Deduplicate {
+                tolerance: Length::new(0.5),
+                angle_tolerance,
+                ..Default::default()
+            };
+            let collection = GridCollection::new(Rect::new(0.0, 100.0, 0.0, 100.0))
+                .with_deduplicate(deduplicate)
+                .with_grid(Grid::new(10.0))
+                .with_grid(Grid::new(10.0).with_theta(0.8));
+            let mut counts = [0; 2];
+            for segment in collection.segments() {
+                counts[segment.grid] += 1;
+            }
+            counts
+        };
+        let [first, second] = counts(1.0);
+        assert_eq!(first, 0);
+        assert!(second > 0);
+        let [first, second] = counts(0.5);
+        assert!(first > 0 && second > 0);
+    }
+}
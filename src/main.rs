@@ -1,10 +1,10 @@
-use std::{f64::consts::FRAC_1_SQRT_2, path::PathBuf};
+use std::{fs::File, io::BufWriter, path::PathBuf};
 
-use clap::Parser;
-use serde::Deserialize;
-use svg::{
-    node::element::{ClipPath, Definitions, Group, Line, Rectangle},
-    Node,
+use anyhow::Context;
+use clap::{Parser, ValueEnum};
+use grid_gen::{
+    Color, DxfRenderer, GridCollection, PageSize, PdfRenderer, PlotterRenderer, PngRenderer,
+    Renderer, Resolution, SvgRenderer,
 };
 
 #[derive(Debug, Parser)]
@@ -13,180 +13,158 @@ struct Args {
     input: PathBuf,
     /// Path of the output file
     output: Option<PathBuf>,
+    /// Format of the output file. Defaults to the format matching the extension of the output
+    /// file, or SVG if no output file is given
+    #[clap(long, value_enum)]
+    format: Option<Format>,
+    /// Also emit an SVG clip-path for the viewable area
+    #[clap(long)]
+    clip_path: bool,
+    /// Page size for PDF output: a3, a4, a5, letter or legal. Defaults to the page of the
+    /// collection, or a4 if it has none. Collections with units are drawn at their actual size,
+    /// and others are scaled to fit
+    #[clap(long, value_parser = parse_page_size)]
+    page_size: Option<PageSize>,
+    /// Turn the page sideways for PDF output
+    #[clap(long)]
+    landscape: bool,
+    /// Resolution for PNG output, taking one unit of the bounds to be 1/96 inch unless the
+    /// collection has units
+    #[clap(long, default_value_t = 96.0)]
+    dpi: f64,
+    /// Width in pixels for PNG output, overriding --dpi
+    #[clap(long)]
+    width: Option<u32>,
+    /// Height in pixels for PNG output, overriding --dpi
+    #[clap(long)]
+    height: Option<u32>,
+    /// Reorder lines to reduce pen-up travel for HPGL and G-code output
+    #[clap(long)]
+    optimize: bool,
+    /// Background color for PNG output
+    #[clap(long, default_value = "transparent", value_parser = parse_color)]
+    background: Color,
+    /// Also write the faces formed by the lines of the grids to this file, as YAML
+    #[clap(long)]
+    faces: Option<PathBuf>,
 }
 
-fn main() -> anyhow::Result<()> {
-    let args = Args::parse();
-    let out_file = args
-        .output
-        .unwrap_or_else(|| args.input.with_extension("svg"));
-    let grids: GridCollection = serde_yaml::from_reader(std::fs::File::open(&args.input)?)?;
-    let doc = grids.to_svg();
-    svg::write(std::fs::File::create(&out_file)?, &doc)?;
-    Ok(())
+fn parse_color(color: &str) -> Result<Color, String> {
+    Color::parse(color).ok_or_else(|| format!("unrecognized color {:?}", color))
 }
 
-#[derive(Clone, Debug, Default, Deserialize)]
-pub struct GridCollection {
-    /// Bounds for the generated SVG
-    bounds: Rect,
-    /// Bounds for the area of the image which will be rendered
-    clip: Option<Rect>,
-    /// Default stroke color
-    stroke: Option<String>,
-    /// Default stroke width
-    stroke_width: Option<f64>,
-    grids: Vec<Grid>,
+fn parse_page_size(name: &str) -> Result<PageSize, String> {
+    PageSize::from_name(name).ok_or_else(|| format!("unknown page size {:?}", name))
 }
 
-impl GridCollection {
-    pub fn to_svg(&self) -> svg::Document {
-        let bounds = self.clip.unwrap_or(self.bounds);
-        assert!(
-            bounds.min_x < bounds.max_x,
-            "min_x: {}, max_x: {}",
-            bounds.min_x,
-            bounds.max_x
-        );
-        assert!(
-            bounds.min_y < bounds.max_y,
-            "min_y: {}, max_y: {}",
-            bounds.min_y,
-            bounds.max_y
-        );
-        let document = svg::Document::new()
-            .set(
-                "viewBox",
-                (
-                    self.bounds.min_x,
-                    self.bounds.min_y,
-                    self.bounds.max_x - self.bounds.min_x,
-                    self.bounds.max_y - self.bounds.min_y,
-                ),
-            )
-            .add(
-                Definitions::new().add(
-                    ClipPath::new().set("id", "viewable-area").add(
-                        Rectangle::new()
-                            .set("x", bounds.min_x)
-                            .set("y", bounds.min_y)
-                            .set("width", bounds.max_x - bounds.min_x)
-                            .set("height", bounds.max_y - bounds.min_y),
-                    ),
-                ),
-            );
-        let mut main_group = Group::new().set("stroke", self.stroke.as_deref().unwrap_or("black"));
-        if let Some(width) = self.stroke_width {
-            main_group.assign("stroke-width", width);
-        }
-        for grid in &self.grids {
-            let mut theta = grid.theta.rem_euclid(360.0);
-            let mut step = grid.step;
-            assert_ne!(step, 0.0);
-            if theta >= 180.0 {
-                theta -= 180.0;
-                step = -step;
-            };
-            let (cos, sin) = cos_sin_degrees(theta);
-            let cx = grid.cx - cos * step * grid.center_position;
-            let cy = grid.cy - sin * step * grid.center_position;
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+enum Format {
+    Svg,
+    Pdf,
+    Png,
+    Dxf,
+    Hpgl,
+    Gcode,
+}
 
-            let mut group = Group::new().set("clip-path", "url(#viewable-area)");
-            if let Some(stroke) = &grid.stroke {
-                group.assign("stroke", &**stroke);
-            }
-            if let Some(width) = grid.stroke_width {
-                group.assign("stroke-width", width);
-            }
-            if (45.0..135.0).contains(&theta) {
-                // more horizontal than vertical
-                assert!(sin >= FRAC_1_SQRT_2);
-                let cot = cos / sin;
-                // project onto the min_x line
-                let y0 = cy + cot * (cx - bounds.min_x);
-                // project onto the max_x line
-                let y1 = cy + cot * (cx - bounds.max_x);
-                let dy = (step / sin).abs();
-                let min_idx = ((bounds.min_y - y0.max(y1)) / dy - 1.0) as i64;
-                let max_idx = ((bounds.max_y - y0.min(y1)) / dy + 1.0) as i64;
-                for i in min_idx..=max_idx {
-                    group.append(
-                        Line::new()
-                            .set("x1", bounds.min_x)
-                            .set("x2", bounds.max_x)
-                            .set("y1", y0 + dy * (i as f64))
-                            .set("y2", y1 + dy * (i as f64)),
-                    );
-                }
-            } else {
-                // more vertical than horizontal
-                assert!(cos.abs() >= FRAC_1_SQRT_2);
-                let tan = sin / cos;
-                // project onto the min_y line
-                let x0 = cx + tan * (cy - bounds.min_y);
-                // project onto the max_y line
-                let x1 = cx + tan * (cy - bounds.max_y);
-                let dx = (step / cos).abs();
-                let min_idx = ((bounds.min_x - x0.max(x1)) / dx - 1.0) as i64;
-                let max_idx = ((bounds.max_x - x0.min(x1)) / dx + 1.0) as i64;
-                for i in min_idx..=max_idx {
-                    group.append(
-                        Line::new()
-                            .set("x1", x0 + dx * (i as f64))
-                            .set("x2", x1 + dx * (i as f64))
-                            .set("y1", bounds.min_y)
-                            .set("y2", bounds.max_y),
-                    );
-                }
-            }
-            main_group.append(group);
-        }
-        document.add(main_group)
+impl Format {
+    fn from_extension(extension: &str) -> Option<Self> {
+        Format::value_variants().iter().copied().find(|format| {
+            format
+                .extensions()
+                .iter()
+                .any(|ext| ext.eq_ignore_ascii_case(extension))
+        })
     }
-}
 
-#[derive(Clone, Copy, Debug, Default, Deserialize)]
-#[serde(rename_all = "kebab-case", deny_unknown_fields)]
-struct Rect {
-    min_x: f64,
-    max_x: f64,
-    min_y: f64,
-    max_y: f64,
-}
+    /// The default extension for files of this format.
+    fn extension(self) -> &'static str {
+        self.extensions()[0]
+    }
 
-#[derive(Clone, Debug, Default, Deserialize)]
-#[serde(default, rename_all = "kebab-case")]
-struct Grid {
-    /// x-coordinate of the center of the grid
-    cx: f64,
-    /// y-coordinate of the center of the grid
-    cy: f64,
-    /// Spacing between adjacent lines
-    step: f64,
-    /// Position of the center point relative to the grid. Integers mean the center point is on a
-    /// grid line, non-integers mean it is somewhere between two grid lines.
-    center_position: f64,
-    /// Rotation clockwise from vertical about the center point, in degrees
-    theta: f64,
-    /// Stroke color
-    stroke: Option<String>,
-    /// Stroke width
-    stroke_width: Option<f64>,
+    fn extensions(self) -> &'static [&'static str] {
+        match self {
+            Format::Svg => &["svg"],
+            Format::Pdf => &["pdf"],
+            Format::Png => &["png"],
+            Format::Dxf => &["dxf"],
+            Format::Hpgl => &["hpgl", "plt", "hpg"],
+            Format::Gcode => &["gcode", "nc", "ngc", "gco"],
+        }
+    }
 }
 
-/// Returns the cos and sin of an angle in degrees, assuming it is in the range 0..180
-fn cos_sin_degrees(theta: f64) -> (f64, f64) {
-    assert!((0.0..180.0).contains(&theta));
-    if theta == 0.0 {
-        (1.0, 0.0)
-    } else if theta == 45.0 {
-        (FRAC_1_SQRT_2, FRAC_1_SQRT_2)
-    } else if theta == 90.0 {
-        (0.0, 1.0)
-    } else if theta == 135.0 {
-        (-FRAC_1_SQRT_2, FRAC_1_SQRT_2)
-    } else {
-        let rad = theta.to_radians();
-        (rad.cos(), rad.sin())
+fn main() -> anyhow::Result<()> {
+    let args = Args::parse();
+    let (format, out_file) = match (args.format, args.output) {
+        (Some(format), Some(output)) => (format, output),
+        (Some(format), None) => (format, args.input.with_extension(format.extension())),
+        (None, Some(output)) => {
+            let format = output
+                .extension()
+                .and_then(|ext| Format::from_extension(ext.to_str()?))
+                .with_context(|| {
+                    format!(
+                        "cannot determine output format of {}; use --format",
+                        output.display()
+                    )
+                })?;
+            (format, output)
+        }
+        (None, None) => (Format::Svg, args.input.with_extension("svg")),
+    };
+    let source = std::fs::read_to_string(&args.input)?;
+    let grids = GridCollection::from_yaml(&source)?;
+    if let Some(path) = &args.faces {
+        serde_yaml::to_writer(BufWriter::new(File::create(path)?), &grids.faces())?;
     }
+    let mut renderer: Box<dyn Renderer> = match format {
+        Format::Svg => {
+            Box::new(SvgRenderer::new().with_clip_path(grids.clip_path || args.clip_path))
+        }
+        Format::Pdf => {
+            let page = match (args.page_size, grids.page) {
+                (Some(size), _) => size,
+                (None, Some(page)) => page.size.oriented(page.orientation),
+                (None, None) => PageSize::A4,
+            };
+            let page = if args.landscape {
+                page.landscape()
+            } else {
+                page
+            };
+            Box::new(PdfRenderer::new(page))
+        }
+        Format::Png => {
+            let resolution = if args.width.is_some() || args.height.is_some() {
+                Resolution::Size {
+                    width: args.width,
+                    height: args.height,
+                }
+            } else {
+                Resolution::Dpi(args.dpi)
+            };
+            Box::new(PngRenderer::new(resolution).with_background(args.background))
+        }
+        Format::Dxf => Box::new(DxfRenderer::new()),
+        Format::Hpgl | Format::Gcode => {
+            let mut settings = grids.plotter.clone().unwrap_or_default();
+            settings.optimize |= args.optimize;
+            let mut renderer = if format == Format::Hpgl {
+                PlotterRenderer::hpgl(settings)
+            } else {
+                PlotterRenderer::gcode(settings)
+            };
+            grids.render(&mut renderer)?;
+            if let Some((before, after)) = renderer.optimization_stats() {
+                eprintln!("before optimization: {}", before);
+                eprintln!("after optimization: {}", after);
+            }
+            renderer.write(&mut BufWriter::new(File::create(&out_file)?))?;
+            return Ok(());
+        }
+    };
+    grids.render(&mut *renderer)?;
+    renderer.write(&mut BufWriter::new(File::create(&out_file)?))?;
+    Ok(())
 }
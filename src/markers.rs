@@ -0,0 +1,317 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    error::{color, non_negative, positive, ErrorKind, GridError},
+    grid::cos_sin_degrees,
+    kind::{line_grids, validate_line_grids},
+    units::{Conversion, Length, ResolveUnits},
+    GridKind, Point, Rect, Segment,
+};
+
+/// Marks drawn where the lines of several grids cross, such as the dots of dot-grid paper.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(default, rename_all = "kebab-case", deny_unknown_fields)]
+pub struct Markers {
+    /// Name of the set of markers, used for layer names in formats which have them
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    pub shape: MarkerShape,
+    /// Diameter of dots, or width and height of crosses and plus marks
+    pub size: Length,
+    /// Indices of the grids whose intersections are marked, or all grids of parallel lines if
+    /// unset
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub grids: Option<Vec<usize>>,
+    /// Number of those grids which must have a line through a point for it to be marked
+    pub min_lines: usize,
+    /// Stroke color, also used to fill dots
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stroke: Option<String>,
+    /// Stroke width of crosses and plus marks
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stroke_width: Option<Length>,
+}
+
+impl Default for Markers {
+    fn default() -> Self {
+        Markers {
+            name: None,
+            shape: MarkerShape::default(),
+            size: Length::new(2.0),
+            grids: None,
+            min_lines: 2,
+            stroke: None,
+            stroke_width: None,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum MarkerShape {
+    /// A filled circle, left out where it doesn't fit inside the clip
+    #[default]
+    Dot,
+    /// Two diagonal strokes
+    Cross,
+    /// A horizontal and a vertical stroke
+    Plus,
+}
+
+/// Maximum distance between two intersections for them to be considered the same point.
+const TOLERANCE: f64 = 1e-6;
+
+impl Markers {
+    /// Marks of the given shape and size where any two grids cross.
+    pub fn new(shape: MarkerShape, size: f64) -> Self {
+        Markers {
+            shape,
+            size: size.into(),
+            ..Default::default()
+        }
+    }
+
+    pub fn with_name(mut self, name: impl Into<String>) -> Self {
+        self.name = Some(name.into());
+        self
+    }
+
+    /// Only marks intersections between the grids with the given indices.
+    pub fn with_grids(mut self, grids: impl IntoIterator<Item = usize>) -> Self {
+        self.grids = Some(grids.into_iter().collect());
+        self
+    }
+
+    /// Only marks points where at least `min_lines` of the grids cross.
+    pub fn with_min_lines(mut self, min_lines: usize) -> Self {
+        self.min_lines = min_lines;
+        self
+    }
+
+    pub fn with_stroke(mut self, stroke: impl Into<String>) -> Self {
+        self.stroke = Some(stroke.into());
+        self
+    }
+
+    pub fn with_stroke_width(mut self, stroke_width: f64) -> Self {
+        self.stroke_width = Some(stroke_width.into());
+        self
+    }
+
+    /// Returns the points within `area` where lines of at least `min_lines` of the selected grids
+    /// cross, ordered by y and then x.
+    pub fn points(&self, grids: &[GridKind], area: Rect) -> Vec<Point> {
+        let selected = line_grids(self.grids.as_deref(), grids);
+        // each line as its unit normal and distance from the origin along it
+        let lines = selected
+            .iter()
+            .map(|grid| {
+                grid.lines(area)
+                    .map(|(index, _)| {
+                        let (theta, offset) = grid.line_position(index);
+                        let (cos, sin) = cos_sin_degrees(theta);
+                        (Point::new(cos, sin), offset)
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+
+        let mut points = PointSet::default();
+        for a in 0..lines.len() {
+            for b in a + 1..lines.len() {
+                for &(normal_a, offset_a) in &lines[a] {
+                    for &(normal_b, offset_b) in &lines[b] {
+                        let det = normal_a.cross(normal_b);
+                        if det.abs() < TOLERANCE {
+                            // parallel lines
+                            continue;
+                        }
+                        let point = Point::new(
+                            (offset_a * normal_b.y - offset_b * normal_a.y) / det,
+                            (normal_a.x * offset_b - normal_b.x * offset_a) / det,
+                        );
+                        if point.x >= area.min_x - TOLERANCE
+                            && point.x <= area.max_x + TOLERANCE
+                            && point.y >= area.min_y - TOLERANCE
+                            && point.y <= area.max_y + TOLERANCE
+                            && selected[a].covers(point)
+                            && selected[b].covers(point)
+                        {
+                            points.insert(point, [a, b]);
+                        }
+                    }
+                }
+            }
+        }
+
+        let mut points = points
+            .points
+            .into_iter()
+            .filter(|(_, grids)| grids.len() >= self.min_lines)
+            .map(|(point, _)| point)
+            .collect::<Vec<_>>();
+        points.sort_by(|a, b| a.y.total_cmp(&b.y).then(a.x.total_cmp(&b.x)));
+        points
+    }
+
+    /// Returns the strokes of a cross or plus mark centered on `point`, clipped to `area`. Dots
+    /// have no strokes.
+    pub fn strokes(&self, point: Point, area: Rect) -> impl Iterator<Item = Segment> {
+        let half = self.size.get() / 2.0;
+        let arms = match self.shape {
+            MarkerShape::Dot => vec![],
+            MarkerShape::Cross => vec![Point::new(half, half), Point::new(half, -half)],
+            MarkerShape::Plus => vec![Point::new(half, 0.0), Point::new(0.0, half)],
+        };
+        arms.into_iter()
+            .filter_map(move |arm| area.clip_segment(Segment::new(point - arm, point + arm)))
+    }
+
+    pub(crate) fn validate(&self, field: &str, grids: &[GridKind]) -> Result<(), GridError> {
+        positive(self.size.get())
+            .map_err(|kind| GridError::new(format!("{}.size", field), kind))?;
+        if let Some(indices) = &self.grids {
+            validate_line_grids(&format!("{}.grids", field), indices, grids)?;
+        }
+        if self.min_lines < 2 {
+            return Err(GridError::new(
+                format!("{}.min-lines", field),
+                ErrorKind::TooSmall {
+                    min: 2.0,
+                    value: self.min_lines as f64,
+                },
+            ));
+        }
+        if let Some(stroke) = &self.stroke {
+            color(stroke).map_err(|kind| GridError::new(format!("{}.stroke", field), kind))?;
+        }
+        if let Some(width) = self.stroke_width {
+            non_negative(width.get())
+                .map_err(|kind| GridError::new(format!("{}.stroke-width", field), kind))?;
+        }
+        Ok(())
+    }
+}
+
+impl ResolveUnits for Markers {
+    fn resolve_units(&mut self, units: &mut Conversion) {
+        self.size.resolve_units(units);
+        self.stroke_width.resolve_units(units);
+    }
+}
+
+/// Intersections found so far, each with the indices of the grids which cross there. Points within
+/// `TOLERANCE` of each other are merged.
+#[derive(Default)]
+struct PointSet {
+    points: Vec<(Point, Vec<usize>)>,
+    /// Indices into `points`, bucketed into cells `TOLERANCE` wide
+    cells: HashMap<(i64, i64), Vec<usize>>,
+}
+
+impl PointSet {
+    fn insert(&mut self, point: Point, grids: [usize; 2]) {
+        let cell = |point: Point| {
+            (
+                (point.x / TOLERANCE).floor() as i64,
+                (point.y / TOLERANCE).floor() as i64,
+            )
+        };
+        let (cx, cy) = cell(point);
+        let existing = (cx - 1..=cx + 1)
+            .flat_map(|x| (cy - 1..=cy + 1).map(move |y| (x, y)))
+            .filter_map(|cell| self.cells.get(&cell))
+            .flatten()
+            .copied()
+            .find(|&i| self.points[i].0.distance(point) <= TOLERANCE);
+        let index = match existing {
+            Some(index) => index,
+            None => {
+                self.points.push((point, Vec::new()));
+                self.cells
+                    .entry((cx, cy))
+                    .or_default()
+                    .push(self.points.len() - 1);
+                self.points.len() - 1
+            }
+        };
+        let found = &mut self.points[index].1;
+        for grid in grids {
+            if !found.contains(&grid) {
+                found.push(grid);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Grid, Region};
+
+    fn square_grid(spacing: f64) -> Vec<GridKind> {
+        vec![
+            Grid::new(spacing).into(),
+            Grid::new(spacing).with_theta(90.0).into(),
+        ]
+    }
+
+    #[test]
+    fn marks_intersections_in_order() {
+        let points = Markers::default().points(&square_grid(10.0), Rect::new(0.0, 25.0, 0.0, 20.0));
+        let expected = [0.0, 10.0, 20.0]
+            .into_iter()
+            .flat_map(|y| [0.0, 10.0, 20.0].map(|x| Point::new(x, y)))
+            .collect::<Vec<_>>();
+        assert_eq!(points.len(), expected.len());
+        for (point, expected) in points.iter().zip(&expected) {
+            assert!(point.distance(*expected) < 1e-9, "{:?}", points);
+        }
+    }
+
+    #[test]
+    fn min_lines_keeps_the_vertices_of_triangles() {
+        // with the lines at 60° twice as far apart, only every other crossing of the lines at 0°
+        // and 120° is a vertex of a triangle
+        let lines = [(0.0, 10.0), (60.0, 20.0), (120.0, 10.0)];
+        let on_lines = |point: Point| {
+            lines
+                .iter()
+                .filter(|&&(theta, spacing)| {
+                    let (cos, sin) = cos_sin_degrees(theta);
+                    let offset = (point.x * cos + point.y * sin) / spacing;
+                    (offset - offset.round()).abs() < 1e-6
+                })
+                .count()
+        };
+        let grids = lines
+            .map(|(theta, spacing)| Grid::new(spacing).with_theta(theta).into())
+            .to_vec();
+        let area = Rect::new(-50.0, 50.0, -50.0, 50.0);
+        let all = Markers::default().points(&grids, area);
+        let vertices = Markers::default().with_min_lines(3).points(&grids, area);
+        assert!(!vertices.is_empty());
+        assert!(vertices.iter().all(|&point| on_lines(point) == 3));
+        assert_eq!(
+            all.iter().filter(|&&point| on_lines(point) == 3).count(),
+            vertices.len()
+        );
+        assert!(all.len() > vertices.len());
+    }
+
+    #[test]
+    fn regions_limit_the_marks() {
+        let mut grids = square_grid(10.0);
+        grids[1] = Grid::new(10.0)
+            .with_theta(90.0)
+            .with_region(Region::from(Rect::new(5.0, 25.0, 5.0, 15.0)))
+            .into();
+        let points = Markers::default().points(&grids, Rect::new(0.0, 30.0, 0.0, 30.0));
+        assert_eq!(
+            points,
+            vec![Point::new(10.0, 10.0), Point::new(20.0, 10.0),]
+        );
+    }
+}
@@ -0,0 +1,324 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    error::{color, non_negative, positive, GridError},
+    grid::cos_sin_degrees,
+    kind::{line_grids, validate_line_grids},
+    units::{Conversion, Length, ResolveUnits},
+    GridKind, Point, Rect,
+};
+
+/// The quasiperiodic rhombic tiling dual to a set of grids of parallel lines, by de Bruijn's
+/// multigrid method. Five grids at 36 degree intervals give a Penrose tiling, and four at 45
+/// degree intervals an Ammann-Beenker tiling. Each grid's `center-position` acts as its shift.
+///
+/// Every point where lines of two grids cross becomes a rhombus. The grids should not have points
+/// where three or more lines meet, or the tiles around them will overlap.
+#[derive(Clone, Debug, Default, PartialEq, Deserialize, Serialize)]
+#[serde(default, rename_all = "kebab-case", deny_unknown_fields)]
+pub struct DeBruijnTiling {
+    /// Name of the grid, used for layer names in formats which have them
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    /// Indices of the grids to take the dual of, or all grids of parallel lines if unset
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub grids: Option<Vec<usize>>,
+    /// Length of the edges of the rhombi. If unset, each grid's edges are `2 * step / n` long for
+    /// `n` grids, which makes the tiling cover about the same area as the grids.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub edge: Option<Length>,
+    /// Stroke color
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stroke: Option<String>,
+    /// Stroke width
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stroke_width: Option<Length>,
+}
+
+/// A grid of lines in the form used by the construction: line `k` is where `index(x) == k`.
+struct Multigrid {
+    normal: Point,
+    step: f64,
+    /// Distance of line 0 from the origin along `normal`
+    offset: f64,
+    /// Edge of the tiles dual to the lines of this grid
+    edge: Point,
+}
+
+impl Multigrid {
+    /// Position of `point` relative to the lines, in steps from line 0.
+    fn index(&self, point: Point) -> f64 {
+        (point.dot(self.normal) - self.offset) / self.step
+    }
+}
+
+impl DeBruijnTiling {
+    /// The tiling dual to all grids of parallel lines in the collection.
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    pub fn with_name(mut self, name: impl Into<String>) -> Self {
+        self.name = Some(name.into());
+        self
+    }
+
+    /// Only uses the grids with the given indices.
+    pub fn with_grids(mut self, grids: impl IntoIterator<Item = usize>) -> Self {
+        self.grids = Some(grids.into_iter().collect());
+        self
+    }
+
+    /// Sets the length of the edges of the rhombi.
+    pub fn with_edge(mut self, edge: f64) -> Self {
+        self.edge = Some(edge.into());
+        self
+    }
+
+    pub fn with_stroke(mut self, stroke: impl Into<String>) -> Self {
+        self.stroke = Some(stroke.into());
+        self
+    }
+
+    pub fn with_stroke_width(mut self, stroke_width: f64) -> Self {
+        self.stroke_width = Some(stroke_width.into());
+        self
+    }
+
+    /// Returns the rhombi of the tiling which cross `area`, clipped to it, each as a list of
+    /// vertices.
+    ///
+    /// The collection must be valid; see `GridCollection::validate`.
+    pub fn tiles(&self, grids: &[GridKind], area: Rect) -> Vec<Vec<Point>> {
+        let selected = line_grids(self.grids.as_deref(), grids);
+        let count = selected.len() as f64;
+        let multigrid = selected
+            .iter()
+            .map(|grid| {
+                let (theta, offset) = grid.line_position(0);
+                let step = grid.line_position(1).1 - offset;
+                let (cos, sin) = cos_sin_degrees(theta);
+                let normal = Point::new(cos, sin);
+                let length = self.edge.map_or(2.0 * step.abs() / count, Length::get);
+                Multigrid {
+                    normal,
+                    step,
+                    offset,
+                    edge: normal * (length * step.signum()),
+                }
+            })
+            .collect::<Vec<_>>();
+
+        // tiles are displaced from the crossings they are dual to by up to about this much
+        let margin = 2.0 * multigrid.iter().map(|g| g.edge.length()).sum::<f64>();
+        let search = Rect::new(
+            area.min_x - margin,
+            area.max_x + margin,
+            area.min_y - margin,
+            area.max_y + margin,
+        );
+        // lines of each grid crossing the search area, as indices
+        let lines = selected
+            .iter()
+            .map(|grid| grid.lines(search).map(|(index, _)| index).collect())
+            .collect::<Vec<Vec<i64>>>();
+        // moves vertices back over the crossings, undoing the offsets of the grids
+        let shift = multigrid.iter().fold(Point::default(), |sum, g| {
+            sum + g.edge * (g.offset / g.step)
+        });
+
+        let mut tiles = Vec::new();
+        for j in 0..multigrid.len() {
+            for l in j + 1..multigrid.len() {
+                let (gj, gl) = (&multigrid[j], &multigrid[l]);
+                let det = gj.normal.cross(gl.normal);
+                if det.abs() < 1e-9 {
+                    continue;
+                }
+                for &kj in &lines[j] {
+                    for &kl in &lines[l] {
+                        let oj = gj.offset + kj as f64 * gj.step;
+                        let ol = gl.offset + kl as f64 * gl.step;
+                        let crossing = Point::new(
+                            (oj * gl.normal.y - ol * gj.normal.y) / det,
+                            (gj.normal.x * ol - gl.normal.x * oj) / det,
+                        );
+                        if !search.contains_point(crossing) {
+                            continue;
+                        }
+                        // vertex of the cell of the multigrid containing the crossing, with
+                        // grids `j` and `l` on the far sides of their lines
+                        let base = multigrid
+                            .iter()
+                            .enumerate()
+                            .filter(|&(m, _)| m != j && m != l)
+                            .fold(shift, |sum, (_, g)| {
+                                sum + g.edge * g.index(crossing).floor()
+                            })
+                            + gj.edge * kj as f64
+                            + gl.edge * kl as f64;
+                        let tile = [
+                            base,
+                            base - gj.edge,
+                            base - gj.edge - gl.edge,
+                            base - gl.edge,
+                        ];
+                        let clipped = area.clip_polygon(&tile);
+                        if !clipped.is_empty() {
+                            tiles.push(clipped);
+                        }
+                    }
+                }
+            }
+        }
+        tiles
+    }
+
+    pub(crate) fn validate(&self, grids: &[GridKind]) -> Result<(), GridError> {
+        if let Some(indices) = &self.grids {
+            validate_line_grids("grids", indices, grids)?;
+        }
+        if let Some(edge) = self.edge {
+            positive(edge.get()).map_err(|kind| GridError::new("edge", kind))?;
+        }
+        if let Some(stroke) = &self.stroke {
+            color(stroke).map_err(|kind| GridError::new("stroke", kind))?;
+        }
+        if let Some(width) = self.stroke_width {
+            non_negative(width.get()).map_err(|kind| GridError::new("stroke-width", kind))?;
+        }
+        Ok(())
+    }
+}
+
+impl ResolveUnits for DeBruijnTiling {
+    fn resolve_units(&mut self, units: &mut Conversion) {
+        self.edge.resolve_units(units);
+        self.stroke_width.resolve_units(units);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+    use crate::Grid;
+
+    const AREA: Rect = Rect {
+        min_x: 0.0,
+        max_x: 100.0,
+        min_y: 0.0,
+        max_y: 100.0,
+    };
+    const EDGE: f64 = 4.0;
+
+    /// A Penrose tiling from five grids, with shifts adding up to an integer.
+    fn penrose() -> Vec<Vec<Point>> {
+        let grids = [0.1, 0.3, 0.15, 0.25, 0.2]
+            .iter()
+            .enumerate()
+            .map(|(i, &shift)| {
+                Grid::new(10.0)
+                    .with_theta(36.0 * i as f64)
+                    .with_center_position(shift)
+                    .into()
+            })
+            .collect::<Vec<GridKind>>();
+        DeBruijnTiling::new().with_edge(EDGE).tiles(&grids, AREA)
+    }
+
+    /// The tiles which weren't clipped, and so are whole rhombi.
+    fn whole(tiles: &[Vec<Point>]) -> Vec<&[Point]> {
+        let inside = |p: &Point| {
+            AREA.min_x < p.x && p.x < AREA.max_x && AREA.min_y < p.y && p.y < AREA.max_y
+        };
+        tiles
+            .iter()
+            .filter(|tile| tile.len() == 4 && tile.iter().all(inside))
+            .map(Vec::as_slice)
+            .collect()
+    }
+
+    fn edges(tile: &[Point]) -> impl Iterator<Item = (Point, Point)> + '_ {
+        (0..tile.len()).map(|i| (tile[i], tile[(i + 1) % tile.len()]))
+    }
+
+    /// Whether two convex polygons overlap by more than a sliver.
+    fn overlap(a: &[Point], b: &[Point]) -> bool {
+        edges(a).chain(edges(b)).all(|(p, q)| {
+            let axis = Point::new(q.y - p.y, p.x - q.x);
+            let range = |polygon: &[Point]| {
+                let along = polygon.iter().map(|&v| v.dot(axis) / axis.length());
+                let min = along.clone().fold(f64::INFINITY, f64::min);
+                (min, along.fold(f64::NEG_INFINITY, f64::max))
+            };
+            let ((min_a, max_a), (min_b, max_b)) = (range(a), range(b));
+            min_a.max(min_b) < max_a.min(max_b) - 1e-6
+        })
+    }
+
+    #[test]
+    fn tiles_are_rhombi() {
+        let tiles = penrose();
+        let whole = whole(&tiles);
+        assert!(whole.len() > 500);
+        for tile in whole {
+            for (p, q) in edges(tile) {
+                assert!((p.distance(q) - EDGE).abs() < 1e-9, "{:?}", tile);
+            }
+            // opposite sides are parallel, and the rhombus isn't flat
+            let (a, b) = (tile[1] - tile[0], tile[2] - tile[1]);
+            assert!((tile[3] - tile[2]).distance(a * -1.0) < 1e-9);
+            assert!(a.cross(b).abs() > 0.1 * EDGE * EDGE);
+        }
+    }
+
+    #[test]
+    fn neighbors_share_edges() {
+        let tiles = penrose();
+        let key = |p: Point| ((p.x * 1e6).round() as i64, (p.y * 1e6).round() as i64);
+        let mut count = HashMap::new();
+        for tile in &tiles {
+            for (p, q) in edges(tile) {
+                let (p, q) = (key(p), key(q));
+                *count.entry((p.min(q), p.max(q))).or_insert(0) += 1;
+            }
+        }
+        // away from the edges of the area, every edge lies between exactly two tiles
+        let margin = 3.0 * EDGE;
+        let inner = |p: Point| {
+            AREA.min_x + margin < p.x
+                && p.x < AREA.max_x - margin
+                && AREA.min_y + margin < p.y
+                && p.y < AREA.max_y - margin
+        };
+        let mut checked = 0;
+        for tile in whole(&tiles)
+            .into_iter()
+            .filter(|t| t.iter().all(|&p| inner(p)))
+        {
+            for (p, q) in edges(tile) {
+                let (p, q) = (key(p), key(q));
+                assert_eq!(count[&(p.min(q), p.max(q))], 2, "{:?}", tile);
+                checked += 1;
+            }
+        }
+        assert!(checked > 1000);
+    }
+
+    #[test]
+    fn penrose_tiles_do_not_overlap() {
+        let tiles = penrose();
+        for (i, a) in tiles.iter().enumerate() {
+            for b in &tiles[i + 1..] {
+                assert!(!overlap(a, b), "{:?} overlaps {:?}", a, b);
+            }
+        }
+        // and they cover the area
+        let area =
+            |tile: &Vec<Point>| edges(tile).map(|(p, q)| p.cross(q)).sum::<f64>().abs() / 2.0;
+        let total = tiles.iter().map(area).sum::<f64>();
+        assert!((total - AREA.width() * AREA.height()).abs() < 1e-6);
+    }
+}
@@ -0,0 +1,262 @@
+use std::fmt;
+
+use crate::{Point, Segment};
+
+/// A sequence of segments drawn with the same pen, in paper coordinates (millimeters, with the
+/// y-axis pointing up).
+#[derive(Clone, Debug, PartialEq)]
+pub struct PenStrokes {
+    pub pen: u32,
+    pub segments: Vec<Segment>,
+}
+
+/// Total distances covered by a plotter, in millimeters.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct PathStats {
+    /// Distance travelled with the pen down
+    pub draw: f64,
+    /// Distance travelled with the pen up, starting and ending at the origin
+    pub travel: f64,
+}
+
+impl PathStats {
+    pub fn measure(strokes: &[PenStrokes]) -> Self {
+        let mut stats = PathStats::default();
+        let mut position = Point::default();
+        for segment in strokes.iter().flat_map(|strokes| &strokes.segments) {
+            stats.travel += position.distance(segment.start);
+            stats.draw += segment.length();
+            position = segment.end;
+        }
+        stats.travel += position.length();
+        stats
+    }
+}
+
+impl fmt::Display for PathStats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "draw {:.1} mm, travel {:.1} mm", self.draw, self.travel)
+    }
+}
+
+/// Reorders strokes to reduce the distance travelled with the pen up. Each group of strokes is
+/// drawn in alternating directions, so that the pen zig-zags across parallel lines rather than
+/// returning to the same side each time. Groups are then merged by pen, in order of each pen's
+/// first use, and within each pen ordered so that each group starts close to where the previous
+/// one ended.
+pub fn optimize(strokes: Vec<PenStrokes>) -> Vec<PenStrokes> {
+    let mut pens: Vec<(u32, Vec<Vec<Segment>>)> = Vec::new();
+    for strokes in strokes {
+        let segments = boustrophedon(strokes.segments);
+        if segments.is_empty() {
+            continue;
+        }
+        match pens.iter_mut().find(|(pen, _)| *pen == strokes.pen) {
+            Some((_, groups)) => groups.push(segments),
+            None => pens.push((strokes.pen, vec![segments])),
+        }
+    }
+    let mut position = Point::default();
+    pens.into_iter()
+        .map(|(pen, mut groups)| {
+            let mut segments = Vec::new();
+            while !groups.is_empty() {
+                let (index, reversed) = nearest_group(&groups, position);
+                let mut group = groups.swap_remove(index);
+                if reversed {
+                    group.reverse();
+                    group.iter_mut().for_each(|s| *s = s.reversed());
+                }
+                position = group.last().map_or(position, |s| s.end);
+                segments.extend(group);
+            }
+            PenStrokes { pen, segments }
+        })
+        .collect()
+}
+
+/// Reverses each segment whose end is closer than its start to the end of the segment before it,
+/// so that the pen zig-zags across parallel lines.
+fn boustrophedon(mut segments: Vec<Segment>) -> Vec<Segment> {
+    for i in 1..segments.len() {
+        let end = segments[i - 1].end;
+        let segment = segments[i];
+        if end.distance(segment.end) < end.distance(segment.start) {
+            segments[i] = segment.reversed();
+        }
+    }
+    segments
+}
+
+/// Finds the group which can be started closest to `position`, and whether it should be drawn
+/// backwards.
+fn nearest_group(groups: &[Vec<Segment>], position: Point) -> (usize, bool) {
+    let mut best = (0, false);
+    let mut best_distance = f64::INFINITY;
+    for (i, group) in groups.iter().enumerate() {
+        let (first, last) = match (group.first(), group.last()) {
+            (Some(first), Some(last)) => (first, last),
+            _ => continue,
+        };
+        for (distance, reversed) in [
+            (position.distance(first.start), false),
+            (position.distance(last.end), true),
+        ] {
+            if distance < best_distance {
+                best_distance = distance;
+                best = (i, reversed);
+            }
+        }
+    }
+    best
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Vertical lines `step` apart, all drawn upwards.
+    fn columns(count: usize, step: f64, height: f64) -> Vec<Segment> {
+        (0..count)
+            .map(|i| Segment::new((i as f64 * step, 0.0), (i as f64 * step, height)))
+            .collect()
+    }
+
+    /// Segments in no particular order or direction.
+    fn scattered(count: usize) -> Vec<Segment> {
+        let mut state = 12345u64;
+        let mut random = move || {
+            state = state
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            (state >> 33) as f64 / (1u64 << 31) as f64 * 100.0
+        };
+        (0..count)
+            .map(|_| Segment::new((random(), random()), (random(), random())))
+            .collect()
+    }
+
+    /// The segments of `strokes` as unordered pairs of endpoints, in a canonical order.
+    fn undirected(strokes: &[PenStrokes]) -> Vec<(u32, [(i64, i64); 2])> {
+        let key = |p: Point| ((p.x * 1e6).round() as i64, (p.y * 1e6).round() as i64);
+        let mut segments = strokes
+            .iter()
+            .flat_map(|strokes| {
+                strokes.segments.iter().map(move |segment| {
+                    let mut ends = [key(segment.start), key(segment.end)];
+                    ends.sort();
+                    (strokes.pen, ends)
+                })
+            })
+            .collect::<Vec<_>>();
+        segments.sort();
+        segments
+    }
+
+    #[test]
+    fn measures_paths() {
+        let strokes = [PenStrokes {
+            pen: 1,
+            segments: vec![
+                Segment::new((0.0, 0.0), (10.0, 0.0)),
+                Segment::new((10.0, 5.0), (0.0, 5.0)),
+            ],
+        }];
+        let stats = PathStats::measure(&strokes);
+        assert_eq!(
+            stats,
+            PathStats {
+                draw: 20.0,
+                travel: 10.0
+            }
+        );
+        assert_eq!(PathStats::measure(&[]), PathStats::default());
+    }
+
+    #[test]
+    fn optimizing_reduces_travel_only() {
+        let inputs = [
+            vec![PenStrokes {
+                pen: 1,
+                segments: columns(20, 5.0, 100.0),
+            }],
+            vec![
+                PenStrokes {
+                    pen: 1,
+                    segments: columns(10, 10.0, 50.0),
+                },
+                PenStrokes {
+                    pen: 2,
+                    segments: scattered(30),
+                },
+                PenStrokes {
+                    pen: 1,
+                    segments: columns(10, 10.0, 80.0).into_iter().rev().collect(),
+                },
+            ],
+            vec![PenStrokes {
+                pen: 3,
+                segments: scattered(200),
+            }],
+        ];
+        for strokes in inputs {
+            let before = PathStats::measure(&strokes);
+            let optimized = optimize(strokes.clone());
+            let after = PathStats::measure(&optimized);
+            assert!(after.travel <= before.travel, "{} -> {}", before, after);
+            assert!((after.draw - before.draw).abs() < 1e-9);
+            assert_eq!(undirected(&optimized), undirected(&strokes));
+        }
+    }
+
+    #[test]
+    fn zig_zags_across_parallel_lines() {
+        let optimized = optimize(vec![PenStrokes {
+            pen: 1,
+            segments: columns(4, 5.0, 100.0),
+        }]);
+        let segments = &optimized[0].segments;
+        for pair in segments.windows(2) {
+            assert_eq!(pair[0].end.y, pair[1].start.y);
+        }
+        // 5 mm between lines, and back to the origin at the end
+        assert_eq!(PathStats::measure(&optimized).travel, 3.0 * 5.0 + 15.0);
+    }
+
+    #[test]
+    fn merges_groups_by_pen() {
+        let group = |pen, x: f64| PenStrokes {
+            pen,
+            segments: vec![Segment::new((x, 0.0), (x, 10.0))],
+        };
+        let optimized = optimize(vec![
+            group(2, 0.0),
+            group(1, 10.0),
+            PenStrokes {
+                pen: 3,
+                segments: Vec::new(),
+            },
+            group(2, 20.0),
+        ]);
+        let pens = optimized
+            .iter()
+            .map(|strokes| strokes.pen)
+            .collect::<Vec<_>>();
+        assert_eq!(pens, vec![2, 1]);
+        assert_eq!(optimized[0].segments.len(), 2);
+    }
+
+    #[test]
+    fn starts_groups_from_the_nearest_end() {
+        let groups = [
+            vec![Segment::new((50.0, 0.0), (60.0, 0.0))],
+            vec![
+                Segment::new((30.0, 0.0), (20.0, 0.0)),
+                Segment::new((20.0, 5.0), (12.0, 5.0)),
+            ],
+        ];
+        assert_eq!(nearest_group(&groups, Point::new(0.0, 0.0)), (1, true));
+        assert_eq!(nearest_group(&groups, Point::new(29.0, 0.0)), (1, false));
+        assert_eq!(nearest_group(&groups, Point::new(70.0, 0.0)), (0, true));
+    }
+}
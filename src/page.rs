@@ -0,0 +1,355 @@
+use std::fmt;
+
+use serde::{
+    de::{self, value::MapAccessDeserializer, MapAccess, Visitor},
+    ser, Deserialize, Deserializer, Serialize, Serializer,
+};
+
+use crate::{
+    error::{non_negative, ErrorKind, GridError},
+    units::{Conversion, Length, ResolveUnits},
+    Rect, Unit,
+};
+
+/// A page which the bounds and clip of a collection can be derived from, unless they are given
+/// explicitly. The origin is at the top left corner inside the margins, or at the center of the
+/// area inside them if `centered` is set.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct Page {
+    pub size: PageSize,
+    #[serde(default)]
+    pub orientation: Orientation,
+    /// May be given as a single length for all sides
+    #[serde(default)]
+    pub margin: Margins,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub centered: bool,
+}
+
+/// Margins on each side of a page. In YAML, they may be given as a single length for all sides,
+/// or as a mapping with any of `top`, `right`, `bottom` and `left`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize)]
+pub struct Margins {
+    pub top: Length,
+    pub right: Length,
+    pub bottom: Length,
+    pub left: Length,
+}
+
+/// The sides of `Margins` given as a mapping, with the unset ones zero.
+#[derive(Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct MarginSides {
+    top: Length,
+    right: Length,
+    bottom: Length,
+    left: Length,
+}
+
+impl<'de> Deserialize<'de> for Margins {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct MarginsVisitor;
+
+        impl<'de> Visitor<'de> for MarginsVisitor {
+            type Value = Margins;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("a length, or a mapping with `top`, `right`, `bottom` and `left`")
+            }
+
+            fn visit_i64<E: de::Error>(self, value: i64) -> Result<Margins, E> {
+                self.visit_f64(value as f64)
+            }
+
+            fn visit_u64<E: de::Error>(self, value: u64) -> Result<Margins, E> {
+                self.visit_f64(value as f64)
+            }
+
+            fn visit_f64<E: de::Error>(self, value: f64) -> Result<Margins, E> {
+                Ok(Margins::uniform(value))
+            }
+
+            fn visit_str<E: de::Error>(self, value: &str) -> Result<Margins, E> {
+                value
+                    .parse::<Length>()
+                    .map(Margins::uniform)
+                    .map_err(|_| E::invalid_value(de::Unexpected::Str(value), &self))
+            }
+
+            fn visit_map<A: MapAccess<'de>>(self, map: A) -> Result<Margins, A::Error> {
+                let sides = MarginSides::deserialize(MapAccessDeserializer::new(map))?;
+                Ok(Margins {
+                    top: sides.top,
+                    right: sides.right,
+                    bottom: sides.bottom,
+                    left: sides.left,
+                })
+            }
+        }
+
+        deserializer.deserialize_any(MarginsVisitor)
+    }
+}
+
+impl Margins {
+    /// The same margin on all sides.
+    pub fn uniform(margin: impl Into<Length>) -> Self {
+        let margin = margin.into();
+        Margins {
+            top: margin,
+            right: margin,
+            bottom: margin,
+            left: margin,
+        }
+    }
+}
+
+impl ResolveUnits for Margins {
+    fn resolve_units(&mut self, units: &mut Conversion) {
+        for side in [
+            &mut self.top,
+            &mut self.right,
+            &mut self.bottom,
+            &mut self.left,
+        ] {
+            side.resolve_units(units);
+        }
+    }
+}
+
+impl ResolveUnits for Page {
+    fn resolve_units(&mut self, units: &mut Conversion) {
+        self.margin.resolve_units(units);
+    }
+}
+
+impl Page {
+    pub fn new(size: PageSize) -> Self {
+        Page {
+            size,
+            ..Default::default()
+        }
+    }
+
+    pub fn with_orientation(mut self, orientation: Orientation) -> Self {
+        self.orientation = orientation;
+        self
+    }
+
+    pub fn with_margin(mut self, margin: Margins) -> Self {
+        self.margin = margin;
+        self
+    }
+
+    /// Puts the origin at the center of the area inside the margins.
+    pub fn with_centered(mut self, centered: bool) -> Self {
+        self.centered = centered;
+        self
+    }
+
+    /// The whole page, in `units`.
+    pub fn bounds(&self, units: Unit) -> Rect {
+        let inside = self.inside(units);
+        Rect::new(
+            inside.min_x - self.margin.left.get(),
+            inside.max_x + self.margin.right.get(),
+            inside.min_y - self.margin.top.get(),
+            inside.max_y + self.margin.bottom.get(),
+        )
+    }
+
+    /// The area inside the margins, in `units`.
+    pub fn inside(&self, units: Unit) -> Rect {
+        let size = self.size.oriented(self.orientation);
+        let margin = self.margin;
+        let width = Unit::Pt.convert(size.width, units) - margin.left.get() - margin.right.get();
+        let height = Unit::Pt.convert(size.height, units) - margin.top.get() - margin.bottom.get();
+        if self.centered {
+            Rect::new(-width / 2.0, width / 2.0, -height / 2.0, height / 2.0)
+        } else {
+            Rect::new(0.0, width, 0.0, height)
+        }
+    }
+
+    pub(crate) fn validate(&self, field: &str, units: Unit) -> Result<(), GridError> {
+        let margin = self.margin;
+        for value in [margin.top, margin.right, margin.bottom, margin.left] {
+            non_negative(value.get())
+                .map_err(|kind| GridError::new(format!("{}.margin", field), kind))?;
+        }
+        let inside = self.inside(units);
+        if inside.width() <= 0.0 || inside.height() <= 0.0 {
+            return Err(GridError::new(
+                format!("{}.margin", field),
+                ErrorKind::MarginsTooWide,
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// A physical page size, in PostScript points (1/72 inch). In YAML, it is given by name; see
+/// `from_name`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PageSize {
+    pub width: f64,
+    pub height: f64,
+}
+
+/// Named page sizes, in the order of `from_name`.
+const NAMES: &[(&str, PageSize)] = &[
+    ("a3", PageSize::A3),
+    ("a4", PageSize::A4),
+    ("a5", PageSize::A5),
+    ("letter", PageSize::LETTER),
+    ("legal", PageSize::LEGAL),
+];
+
+impl PageSize {
+    pub const A3: PageSize = PageSize::mm(297.0, 420.0);
+    pub const A4: PageSize = PageSize::mm(210.0, 297.0);
+    pub const A5: PageSize = PageSize::mm(148.0, 210.0);
+    pub const LETTER: PageSize = PageSize::new(612.0, 792.0);
+    pub const LEGAL: PageSize = PageSize::new(612.0, 1008.0);
+
+    pub const fn new(width: f64, height: f64) -> Self {
+        PageSize { width, height }
+    }
+
+    /// A page size given in millimeters.
+    pub const fn mm(width: f64, height: f64) -> Self {
+        PageSize::new(width * 72.0 / 25.4, height * 72.0 / 25.4)
+    }
+
+    /// Looks up a named page size (`a3`, `a4`, `a5`, `letter` or `legal`), ignoring case.
+    pub fn from_name(name: &str) -> Option<Self> {
+        NAMES
+            .iter()
+            .find(|(known, _)| known.eq_ignore_ascii_case(name))
+            .map(|&(_, size)| size)
+    }
+
+    /// The name of this page size in either orientation, if it has one.
+    pub fn name(self) -> Option<&'static str> {
+        NAMES
+            .iter()
+            .find(|(_, size)| size.portrait() == self.portrait())
+            .map(|&(name, _)| name)
+    }
+
+    /// The same page size, with the longer side horizontal.
+    pub fn landscape(self) -> Self {
+        PageSize::new(self.width.max(self.height), self.width.min(self.height))
+    }
+
+    /// The same page size, with the longer side vertical.
+    pub fn portrait(self) -> Self {
+        PageSize::new(self.width.min(self.height), self.width.max(self.height))
+    }
+
+    /// The same page size, turned to `orientation`.
+    pub fn oriented(self, orientation: Orientation) -> Self {
+        match orientation {
+            Orientation::Portrait => self.portrait(),
+            Orientation::Landscape => self.landscape(),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Orientation {
+    /// The longer side is vertical
+    #[default]
+    Portrait,
+    /// The longer side is horizontal
+    Landscape,
+}
+
+impl Serialize for PageSize {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let name = self
+            .name()
+            .ok_or_else(|| ser::Error::custom("only named page sizes can be serialized"))?;
+        serializer.serialize_str(name)
+    }
+}
+
+impl<'de> Deserialize<'de> for PageSize {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let name = String::deserialize(deserializer)?;
+        PageSize::from_name(&name).ok_or_else(|| {
+            de::Error::invalid_value(de::Unexpected::Str(&name), &"a3, a4, a5, letter or legal")
+        })
+    }
+}
+
+impl Default for PageSize {
+    fn default() -> Self {
+        PageSize::A4
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn margins(top: f64, right: f64, bottom: f64, left: f64) -> Margins {
+        Margins {
+            top: top.into(),
+            right: right.into(),
+            bottom: bottom.into(),
+            left: left.into(),
+        }
+    }
+
+    #[test]
+    fn margins_surround_the_inside() {
+        let page = Page::new(PageSize::LETTER).with_margin(margins(10.0, 20.0, 30.0, 40.0));
+        assert_eq!(page.inside(Unit::Pt), Rect::new(0.0, 552.0, 0.0, 752.0));
+        assert_eq!(page.bounds(Unit::Pt), Rect::new(-40.0, 572.0, -10.0, 782.0));
+        let page = page.with_orientation(Orientation::Landscape);
+        assert_eq!(page.inside(Unit::Pt), Rect::new(0.0, 732.0, 0.0, 572.0));
+        // margins are in the document's units
+        let page = Page::new(PageSize::A4).with_margin(Margins::uniform(10.0));
+        let bounds = page.bounds(Unit::Mm);
+        assert!((bounds.width() - 210.0).abs() < 1e-9);
+        assert!((bounds.height() - 297.0).abs() < 1e-9);
+        assert!((page.inside(Unit::Mm).width() - 190.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn centering_puts_the_origin_in_the_middle_of_the_inside() {
+        let page = Page::new(PageSize::LETTER)
+            .with_margin(margins(10.0, 20.0, 30.0, 40.0))
+            .with_centered(true);
+        assert_eq!(
+            page.inside(Unit::Pt),
+            Rect::new(-276.0, 276.0, -376.0, 376.0)
+        );
+        // the page isn't centered on the origin unless its margins are even
+        assert_eq!(
+            page.bounds(Unit::Pt),
+            Rect::new(-316.0, 296.0, -386.0, 406.0)
+        );
+    }
+
+    #[test]
+    fn rejects_margins_wider_than_the_page() {
+        let validate = |margin| {
+            Page::new(PageSize::LETTER)
+                .with_margin(margin)
+                .validate("page", Unit::Pt)
+                .map_err(|error| (error.field, error.kind))
+        };
+        let too_wide = Err(("page.margin".into(), ErrorKind::MarginsTooWide));
+        assert_eq!(validate(margins(0.0, 300.0, 0.0, 312.0)), too_wide);
+        assert_eq!(validate(margins(400.0, 0.0, 400.0, 0.0)), too_wide);
+        assert_eq!(validate(margins(500.0, 0.0, 0.0, 0.0)), Ok(()));
+        assert_eq!(
+            validate(margins(0.0, 0.0, -1.0, 0.0)),
+            Err(("page.margin".into(), ErrorKind::Negative(-1.0)))
+        );
+    }
+}
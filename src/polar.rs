@@ -0,0 +1,320 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    error::{color, finite, non_negative, positive, ErrorKind, GridError},
+    geometry::{Arc, Point, Segment},
+    units::{Conversion, Length, ResolveUnits},
+    Rect,
+};
+
+/// A grid of concentric circles and radial spokes around a center point, such as a dial.
+#[derive(Clone, Debug, Default, PartialEq, Deserialize, Serialize)]
+#[serde(default, rename_all = "kebab-case", deny_unknown_fields)]
+pub struct PolarGrid {
+    /// Name of the grid, used for layer names in formats which have them
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    /// x-coordinate of the center of the grid
+    pub cx: Length,
+    /// y-coordinate of the center of the grid
+    pub cy: Length,
+    /// Difference in radius between adjacent circles, or no circles if unset
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub radial_step: Option<Length>,
+    /// Angle between adjacent spokes in degrees, or no spokes if unset
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub angular_step: Option<f64>,
+    /// Angle of one of the spokes, in degrees clockwise from the positive x-axis
+    pub theta: f64,
+    /// Radius at which the circles and spokes start
+    pub inner_radius: Length,
+    /// Radius at which the circles and spokes end, or the edge of the viewable area if unset
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub outer_radius: Option<Length>,
+    /// Stroke color
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stroke: Option<String>,
+    /// Stroke width
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stroke_width: Option<Length>,
+}
+
+impl PolarGrid {
+    /// Creates a polar grid centered on `(cx, cy)`, with neither circles nor spokes.
+    pub fn new(cx: f64, cy: f64) -> Self {
+        PolarGrid {
+            cx: cx.into(),
+            cy: cy.into(),
+            ..Default::default()
+        }
+    }
+
+    pub fn with_name(mut self, name: impl Into<String>) -> Self {
+        self.name = Some(name.into());
+        self
+    }
+
+    /// Adds circles spaced `radial_step` apart.
+    pub fn with_radial_step(mut self, radial_step: f64) -> Self {
+        self.radial_step = Some(radial_step.into());
+        self
+    }
+
+    /// Adds spokes spaced `angular_step` degrees apart.
+    pub fn with_angular_step(mut self, angular_step: f64) -> Self {
+        self.angular_step = Some(angular_step);
+        self
+    }
+
+    /// Sets the angle of one of the spokes, in degrees clockwise from the positive x-axis.
+    pub fn with_theta(mut self, theta: f64) -> Self {
+        self.theta = theta;
+        self
+    }
+
+    /// Limits the circles and spokes to the given range of radii.
+    pub fn with_radii(mut self, inner: f64, outer: Option<f64>) -> Self {
+        self.inner_radius = inner.into();
+        self.outer_radius = outer.map(Length::new);
+        self
+    }
+
+    pub fn with_stroke(mut self, stroke: impl Into<String>) -> Self {
+        self.stroke = Some(stroke.into());
+        self
+    }
+
+    pub fn with_stroke_width(mut self, stroke_width: f64) -> Self {
+        self.stroke_width = Some(stroke_width.into());
+        self
+    }
+
+    pub fn center(&self) -> Point {
+        Point::new(self.cx.get(), self.cy.get())
+    }
+
+    /// Range of radii covered by the grid within `area`.
+    fn radii(&self, area: Rect) -> (f64, f64) {
+        let center = self.center();
+        let nearest = Point::new(
+            center.x.clamp(area.min_x, area.max_x),
+            center.y.clamp(area.min_y, area.max_y),
+        );
+        let farthest = Point::new(
+            if center.x - area.min_x > area.max_x - center.x {
+                area.min_x
+            } else {
+                area.max_x
+            },
+            if center.y - area.min_y > area.max_y - center.y {
+                area.min_y
+            } else {
+                area.max_y
+            },
+        );
+        let max = center.distance(farthest);
+        (
+            self.inner_radius.get().max(center.distance(nearest)),
+            self.outer_radius.map_or(max, |outer| outer.get().min(max)),
+        )
+    }
+
+    /// Returns the circles of this grid which cross `area`, clipped to it, from the innermost
+    /// outwards. Each arc is paired with the index of its circle, which has radius
+    /// `index * radial_step`.
+    ///
+    /// The grid must be valid; see `GridCollection::validate`.
+    pub fn circles(&self, area: Rect) -> impl Iterator<Item = (i64, Arc)> + '_ {
+        let (min, max) = self.radii(area);
+        let (first, last) = match self.radial_step.map(Length::get) {
+            Some(step) => (
+                (min / step).ceil().max(1.0) as i64,
+                (max / step).floor() as i64,
+            ),
+            None => (1, 0),
+        };
+        (first..=last).flat_map(move |i| {
+            let step = self.radial_step.unwrap_or_default().get();
+            area.clip_arc(Arc::circle(self.center(), step * i as f64))
+                .into_iter()
+                .map(move |arc| (i, arc))
+        })
+    }
+
+    /// Returns the spokes of this grid which cross `area`, clipped to it, in order of increasing
+    /// angle. Each spoke is paired with its index: spoke `i` lies at `theta + i * angular_step`
+    /// degrees.
+    ///
+    /// The grid must be valid; see `GridCollection::validate`.
+    pub fn spokes(&self, area: Rect) -> impl Iterator<Item = (i64, Segment)> + '_ {
+        let (min, max) = self.radii(area);
+        let count = match self.angular_step {
+            // leave out a last spoke which would coincide with the first
+            Some(step) if min < max => ((360.0 / step) - 1e-9).ceil() as i64,
+            _ => 0,
+        };
+        (0..count).filter_map(move |i| {
+            let angle =
+                (self.theta + self.angular_step.unwrap_or_default() * i as f64).to_radians();
+            let direction = Point::new(angle.cos(), angle.sin());
+            let spoke = Segment::new(
+                self.center() + direction * min,
+                self.center() + direction * max,
+            );
+            area.clip_segment(spoke).map(|segment| (i, segment))
+        })
+    }
+
+    pub(crate) fn validate(&self) -> Result<(), GridError> {
+        for (name, value) in [
+            ("cx", self.cx.get()),
+            ("cy", self.cy.get()),
+            ("theta", self.theta),
+        ] {
+            finite(value).map_err(|kind| GridError::new(name, kind))?;
+        }
+        if let Some(step) = self.radial_step {
+            positive(step.get()).map_err(|kind| GridError::new("radial-step", kind))?;
+        }
+        if let Some(step) = self.angular_step {
+            positive(step).map_err(|kind| GridError::new("angular-step", kind))?;
+        }
+        let inner = self.inner_radius.get();
+        non_negative(inner).map_err(|kind| GridError::new("inner-radius", kind))?;
+        if let Some(outer) = self.outer_radius.map(Length::get) {
+            finite(outer).map_err(|kind| GridError::new("outer-radius", kind))?;
+            if outer < inner {
+                return Err(GridError::new(
+                    "outer-radius",
+                    ErrorKind::TooSmall {
+                        min: inner,
+                        value: outer,
+                    },
+                ));
+            }
+        }
+        if let Some(stroke) = &self.stroke {
+            color(stroke).map_err(|kind| GridError::new("stroke", kind))?;
+        }
+        if let Some(width) = self.stroke_width {
+            non_negative(width.get()).map_err(|kind| GridError::new("stroke-width", kind))?;
+        }
+        Ok(())
+    }
+}
+
+impl ResolveUnits for PolarGrid {
+    fn resolve_units(&mut self, units: &mut Conversion) {
+        self.cx.resolve_units(units);
+        self.cy.resolve_units(units);
+        self.radial_step.resolve_units(units);
+        self.inner_radius.resolve_units(units);
+        self.outer_radius.resolve_units(units);
+        self.stroke_width.resolve_units(units);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn inside(area: Rect, point: Point) -> bool {
+        point.x >= area.min_x - 1e-9
+            && point.x <= area.max_x + 1e-9
+            && point.y >= area.min_y - 1e-9
+            && point.y <= area.max_y + 1e-9
+    }
+
+    #[test]
+    fn circles_lie_between_the_radii() {
+        let area = Rect::new(0.0, 100.0, 0.0, 100.0);
+        let grid = PolarGrid::new(50.0, 50.0)
+            .with_radial_step(10.0)
+            .with_radii(15.0, Some(35.0));
+        let circles = grid.circles(area).collect::<Vec<_>>();
+        assert_eq!(
+            circles,
+            vec![
+                (2, Arc::circle((50.0, 50.0), 20.0)),
+                (3, Arc::circle((50.0, 50.0), 30.0)),
+            ]
+        );
+        // radii on the limits are included
+        let grid = grid.with_radii(20.0, Some(30.0));
+        assert_eq!(grid.circles(area).count(), 2);
+    }
+
+    #[test]
+    fn circles_are_clipped_to_the_area() {
+        let area = Rect::new(0.0, 100.0, 0.0, 100.0);
+        let grid = PolarGrid::new(0.0, 0.0).with_radial_step(10.0);
+        let circles = grid.circles(area).collect::<Vec<_>>();
+        // out to the far corner, at 141
+        let indices = circles.iter().map(|&(i, _)| i).collect::<Vec<_>>();
+        assert_eq!(indices, (1..=14).collect::<Vec<_>>());
+        for (i, arc) in &circles {
+            assert!(inside(area, arc.start_point()) && inside(area, arc.end_point()));
+            assert!(inside(area, arc.at(arc.start + arc.sweep / 2.0)));
+            if *i <= 10 {
+                assert!((arc.sweep - 90.0).abs() < 1e-9);
+            } else {
+                assert!(arc.sweep < 90.0);
+            }
+        }
+    }
+
+    #[test]
+    fn spokes_go_around_once() {
+        let area = Rect::new(0.0, 100.0, 0.0, 100.0);
+        let count = |step| {
+            PolarGrid::new(50.0, 50.0)
+                .with_angular_step(step)
+                .spokes(area)
+                .count()
+        };
+        assert_eq!(count(30.0), 12);
+        assert_eq!(count(45.0), 8);
+        // the last spoke is left out only when it would meet the first
+        assert_eq!(count(7.0), 52);
+    }
+
+    #[test]
+    fn spokes_run_between_the_radii() {
+        let area = Rect::new(0.0, 100.0, 0.0, 100.0);
+        let grid = PolarGrid::new(50.0, 50.0)
+            .with_angular_step(90.0)
+            .with_radii(10.0, Some(30.0));
+        let spokes = grid.spokes(area).collect::<Vec<_>>();
+        assert_eq!(spokes.len(), 4);
+        let (i, spoke) = spokes[0];
+        assert_eq!(i, 0);
+        assert!(spoke.start.distance(Point::new(60.0, 50.0)) < 1e-9);
+        assert!(spoke.end.distance(Point::new(80.0, 50.0)) < 1e-9);
+        for (_, spoke) in &spokes {
+            assert!((spoke.start.distance(grid.center()) - 10.0).abs() < 1e-9);
+            assert!((spoke.end.distance(grid.center()) - 30.0).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn spokes_are_clipped_to_the_area() {
+        let area = Rect::new(0.0, 100.0, 0.0, 100.0);
+        let grid = PolarGrid::new(0.0, 0.0).with_angular_step(30.0);
+        let spokes = grid.spokes(area).collect::<Vec<_>>();
+        // only the spokes from 0° to 90° cross the area
+        let indices = spokes.iter().map(|&(i, _)| i).collect::<Vec<_>>();
+        assert_eq!(indices, vec![0, 1, 2, 3]);
+        for (_, spoke) in &spokes {
+            assert!(inside(area, spoke.start) && inside(area, spoke.end));
+            assert!(spoke.start.distance(grid.center()) < 1e-9);
+            assert!(spoke.length() >= 100.0 - 1e-9);
+        }
+        // a center outside the area starts the spokes at its nearest edge
+        let grid = PolarGrid::new(-50.0, 50.0).with_angular_step(90.0);
+        let spokes = grid.spokes(area).collect::<Vec<_>>();
+        assert_eq!(spokes.len(), 1);
+        assert_eq!(spokes[0].0, 0);
+        assert!(spokes[0].1.start.distance(Point::new(0.0, 50.0)) < 1e-9);
+        assert!(spokes[0].1.end.distance(Point::new(100.0, 50.0)) < 1e-9);
+    }
+}
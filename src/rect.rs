@@ -0,0 +1,239 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    error::{finite, ErrorKind, GridError},
+    geometry::{Arc, Point, Segment},
+    region::Region,
+    units::{Conversion, Length, ResolveUnits},
+};
+
+/// An axis-aligned rectangle. Rectangles read from a collection have edges of type `Length`, and
+/// are used as `Rect<f64>` once their units are resolved.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct Rect<T = f64> {
+    pub min_x: T,
+    pub max_x: T,
+    pub min_y: T,
+    pub max_y: T,
+}
+
+impl<T> Rect<T> {
+    /// Applies `f` to each edge.
+    pub fn map<U>(self, mut f: impl FnMut(T) -> U) -> Rect<U> {
+        Rect {
+            min_x: f(self.min_x),
+            max_x: f(self.max_x),
+            min_y: f(self.min_y),
+            max_y: f(self.max_y),
+        }
+    }
+}
+
+impl Rect<Length> {
+    /// The rectangle in the units of the collection; see `Length::get`.
+    pub fn get(self) -> Rect {
+        self.map(Length::get)
+    }
+}
+
+impl<T: ResolveUnits> ResolveUnits for Rect<T> {
+    fn resolve_units(&mut self, units: &mut Conversion) {
+        for edge in [
+            &mut self.min_x,
+            &mut self.max_x,
+            &mut self.min_y,
+            &mut self.max_y,
+        ] {
+            edge.resolve_units(units);
+        }
+    }
+}
+
+impl From<Rect> for Rect<Length> {
+    fn from(rect: Rect) -> Self {
+        rect.map(Length::new)
+    }
+}
+
+impl Rect {
+    pub fn new(min_x: f64, max_x: f64, min_y: f64, max_y: f64) -> Self {
+        Rect {
+            min_x,
+            max_x,
+            min_y,
+            max_y,
+        }
+    }
+
+    pub fn width(&self) -> f64 {
+        self.max_x - self.min_x
+    }
+
+    pub fn height(&self) -> f64 {
+        self.max_y - self.min_y
+    }
+
+    pub(crate) fn validate(&self, field: &str) -> Result<(), GridError> {
+        for (name, value) in [
+            ("min-x", self.min_x),
+            ("max-x", self.max_x),
+            ("min-y", self.min_y),
+            ("max-y", self.max_y),
+        ] {
+            finite(value).map_err(|kind| GridError::new(format!("{}.{}", field, name), kind))?;
+        }
+        for (axis, min, max) in [('x', self.min_x, self.max_x), ('y', self.min_y, self.max_y)] {
+            if min >= max {
+                return Err(GridError::new(
+                    field,
+                    ErrorKind::Inverted { axis, min, max },
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Whether `point` lies within this rectangle, or on its edge.
+    pub fn contains_point(&self, point: Point) -> bool {
+        (self.min_x..=self.max_x).contains(&point.x) && (self.min_y..=self.max_y).contains(&point.y)
+    }
+
+    /// Whether `other` lies entirely within this rectangle.
+    pub fn contains(&self, other: &Rect) -> bool {
+        self.min_x <= other.min_x
+            && other.max_x <= self.max_x
+            && self.min_y <= other.min_y
+            && other.max_y <= self.max_y
+    }
+
+    /// Clips `segment` to this rectangle, using the Liang-Barsky algorithm. Returns `None` if no
+    /// part of the segment lies inside the rectangle.
+    pub fn clip_segment(&self, segment: Segment) -> Option<Segment> {
+        let (t0, t1) = self.clip_interval(segment)?;
+        Some(Segment::new(segment.at(t0), segment.at(t1)))
+    }
+
+    /// Returns the range of `t` for which `segment.at(t)` lies inside this rectangle, if any.
+    pub(crate) fn clip_interval(&self, segment: Segment) -> Option<(f64, f64)> {
+        let Point { x: x1, y: y1 } = segment.start;
+        let Point { x: dx, y: dy } = segment.end - segment.start;
+        let mut t0 = 0.0_f64;
+        let mut t1 = 1.0_f64;
+        for (p, q) in [
+            (-dx, x1 - self.min_x),
+            (dx, self.max_x - x1),
+            (-dy, y1 - self.min_y),
+            (dy, self.max_y - y1),
+        ] {
+            if p == 0.0 {
+                // parallel to this edge; reject if outside it
+                if q < 0.0 {
+                    return None;
+                }
+            } else {
+                let t = q / p;
+                if p < 0.0 {
+                    t0 = t0.max(t);
+                } else {
+                    t1 = t1.min(t);
+                }
+            }
+        }
+        if t0 < t1 {
+            Some((t0, t1))
+        } else {
+            None
+        }
+    }
+
+    /// Clips the polygon with vertices `points` to this rectangle, using the Sutherland-Hodgman
+    /// algorithm. Returns an empty list if no part of the polygon lies inside the rectangle.
+    pub fn clip_polygon(&self, points: &[Point]) -> Vec<Point> {
+        let mut polygon = points.to_vec();
+        // each edge as a function which is non-negative on the inside of it
+        let edges: [&dyn Fn(Point) -> f64; 4] = [
+            &|p| p.x - self.min_x,
+            &|p| self.max_x - p.x,
+            &|p| p.y - self.min_y,
+            &|p| self.max_y - p.y,
+        ];
+        for inside in edges {
+            let input = std::mem::take(&mut polygon);
+            for (i, &current) in input.iter().enumerate() {
+                let previous = input[(i + input.len() - 1) % input.len()];
+                let (d0, d1) = (inside(previous), inside(current));
+                if (d0 >= 0.0) != (d1 >= 0.0) {
+                    polygon.push(Segment::new(previous, current).at(d0 / (d0 - d1)));
+                }
+                if d1 >= 0.0 {
+                    polygon.push(current);
+                }
+            }
+        }
+        if polygon.len() < 3 {
+            polygon.clear();
+        }
+        polygon
+    }
+
+    /// Clips `arc` to this rectangle, returning the parts of it which lie inside the rectangle in
+    /// order along the arc. A whole circle is returned as is if it lies entirely inside.
+    pub fn clip_arc(&self, arc: Arc) -> Vec<Arc> {
+        Region::from(*self).clip_arc(arc)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RECT: Rect = Rect {
+        min_x: 0.0,
+        max_x: 10.0,
+        min_y: 0.0,
+        max_y: 20.0,
+    };
+
+    #[test]
+    fn clips_segments() {
+        assert_eq!(
+            RECT.clip_segment(Segment::new((-5.0, 5.0), (15.0, 5.0))),
+            Some(Segment::new((0.0, 5.0), (10.0, 5.0)))
+        );
+        // diagonal, leaving through the top
+        assert_eq!(
+            RECT.clip_segment(Segment::new((5.0, 15.0), (15.0, 25.0))),
+            Some(Segment::new((5.0, 15.0), (10.0, 20.0)))
+        );
+        // reversed segments keep their direction
+        assert_eq!(
+            RECT.clip_segment(Segment::new((5.0, 30.0), (5.0, -10.0))),
+            Some(Segment::new((5.0, 20.0), (5.0, 0.0)))
+        );
+        let inside = Segment::new((1.0, 1.0), (9.0, 19.0));
+        assert_eq!(RECT.clip_segment(inside), Some(inside));
+    }
+
+    #[test]
+    fn rejects_segments_outside() {
+        assert_eq!(
+            RECT.clip_segment(Segment::new((11.0, 0.0), (11.0, 20.0))),
+            None
+        );
+        assert_eq!(
+            RECT.clip_segment(Segment::new((-5.0, 5.0), (-1.0, 5.0))),
+            None
+        );
+        // crosses the corner's diagonal, but misses the rectangle
+        assert_eq!(
+            RECT.clip_segment(Segment::new((8.0, -5.0), (15.0, 2.0))),
+            None
+        );
+        // touches a corner only
+        assert_eq!(
+            RECT.clip_segment(Segment::new((5.0, -5.0), (15.0, 5.0))),
+            None
+        );
+    }
+}
@@ -0,0 +1,881 @@
+use std::{fmt, marker::PhantomData};
+
+use serde::{
+    de::{self, value::MapAccessDeserializer, MapAccess, Visitor},
+    Deserialize, Deserializer, Serialize,
+};
+
+use crate::{
+    error::{finite, positive, ErrorKind, GridError},
+    geometry::{Arc, Point, Segment},
+    grid::cos_sin_degrees,
+    units::{Conversion, Length, ResolveUnits},
+    Rect,
+};
+
+/// An area which lines can be limited to: a shape, less any holes in it. In YAML, the shape is
+/// given by one of the keys `rect`, `polygon`, `circle` or `ellipse`, next to an optional list of
+/// `holes`.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(into = "RegionRepr")]
+pub struct Region {
+    pub shape: Shape,
+    pub holes: Vec<Shape>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(into = "ShapeRepr")]
+pub enum Shape {
+    Rect(Rect<Length>),
+    /// A polygon with the given vertices, which may be concave
+    Polygon(Vec<Point<Length>>),
+    Circle(Circle),
+    Ellipse(Ellipse),
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct Circle {
+    pub cx: Length,
+    pub cy: Length,
+    pub radius: Length,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct Ellipse {
+    pub cx: Length,
+    pub cy: Length,
+    /// Radius along the x-axis, before rotating
+    pub rx: Length,
+    /// Radius along the y-axis, before rotating
+    pub ry: Length,
+    /// Rotation clockwise about the center, in degrees
+    #[serde(default)]
+    pub theta: f64,
+}
+
+#[derive(Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+struct ShapeRepr {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    rect: Option<Rect<Length>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    polygon: Option<Vec<Point<Length>>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    circle: Option<Circle>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    ellipse: Option<Ellipse>,
+}
+
+#[derive(Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+struct RegionRepr {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    rect: Option<Rect<Length>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    polygon: Option<Vec<Point<Length>>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    circle: Option<Circle>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    ellipse: Option<Ellipse>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    holes: Vec<Shape>,
+}
+
+/// A clip, which may be a `Rect` as well as a region.
+#[derive(Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+struct ClipRepr {
+    min_x: Option<Length>,
+    max_x: Option<Length>,
+    min_y: Option<Length>,
+    max_y: Option<Length>,
+    rect: Option<Rect<Length>>,
+    polygon: Option<Vec<Point<Length>>>,
+    circle: Option<Circle>,
+    ellipse: Option<Ellipse>,
+    #[serde(default)]
+    holes: Vec<Shape>,
+}
+
+/// Deserializes a mapping as `R`, and converts it to `T`. Unlike `#[serde(try_from)]`, this
+/// converts while the mapping is being read, so that errors point at it.
+struct Converted<R, T>(PhantomData<(R, T)>);
+
+impl<R, T> Converted<R, T> {
+    fn deserialize<'de, D>(deserializer: D) -> Result<T, D::Error>
+    where
+        D: Deserializer<'de>,
+        R: Deserialize<'de>,
+        T: TryFrom<R, Error = &'static str>,
+    {
+        deserializer.deserialize_map(Converted(PhantomData))
+    }
+}
+
+impl<'de, R, T> Visitor<'de> for Converted<R, T>
+where
+    R: Deserialize<'de>,
+    T: TryFrom<R, Error = &'static str>,
+{
+    type Value = T;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a mapping")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, map: A) -> Result<T, A::Error> {
+        let repr = R::deserialize(MapAccessDeserializer::new(map))?;
+        T::try_from(repr).map_err(de::Error::custom)
+    }
+}
+
+impl<'de> Deserialize<'de> for Shape {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Converted::<ShapeRepr, Shape>::deserialize(deserializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Region {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Converted::<RegionRepr, Region>::deserialize(deserializer)
+    }
+}
+
+impl TryFrom<ShapeRepr> for Shape {
+    type Error = &'static str;
+
+    fn try_from(repr: ShapeRepr) -> Result<Self, Self::Error> {
+        let mut shapes = [
+            repr.rect.map(Shape::Rect),
+            repr.polygon.map(Shape::Polygon),
+            repr.circle.map(Shape::Circle),
+            repr.ellipse.map(Shape::Ellipse),
+        ]
+        .into_iter()
+        .flatten();
+        match (shapes.next(), shapes.next()) {
+            (Some(shape), None) => Ok(shape),
+            _ => Err("expected exactly one of `rect`, `polygon`, `circle` or `ellipse`"),
+        }
+    }
+}
+
+impl From<Shape> for ShapeRepr {
+    fn from(shape: Shape) -> Self {
+        let mut repr = ShapeRepr {
+            rect: None,
+            polygon: None,
+            circle: None,
+            ellipse: None,
+        };
+        match shape {
+            Shape::Rect(rect) => repr.rect = Some(rect),
+            Shape::Polygon(points) => repr.polygon = Some(points),
+            Shape::Circle(circle) => repr.circle = Some(circle),
+            Shape::Ellipse(ellipse) => repr.ellipse = Some(ellipse),
+        }
+        repr
+    }
+}
+
+impl TryFrom<RegionRepr> for Region {
+    type Error = &'static str;
+
+    fn try_from(repr: RegionRepr) -> Result<Self, Self::Error> {
+        let shape = Shape::try_from(ShapeRepr {
+            rect: repr.rect,
+            polygon: repr.polygon,
+            circle: repr.circle,
+            ellipse: repr.ellipse,
+        })?;
+        Ok(Region {
+            shape,
+            holes: repr.holes,
+        })
+    }
+}
+
+impl TryFrom<ClipRepr> for Region {
+    type Error = &'static str;
+
+    fn try_from(repr: ClipRepr) -> Result<Self, Self::Error> {
+        let edges = [repr.min_x, repr.max_x, repr.min_y, repr.max_y];
+        let region = RegionRepr {
+            rect: repr.rect,
+            polygon: repr.polygon,
+            circle: repr.circle,
+            ellipse: repr.ellipse,
+            holes: repr.holes,
+        };
+        match edges {
+            [None, None, None, None] => Region::try_from(region),
+            [Some(min_x), Some(max_x), Some(min_y), Some(max_y)]
+                if region.rect.is_none()
+                    && region.polygon.is_none()
+                    && region.circle.is_none()
+                    && region.ellipse.is_none()
+                    && region.holes.is_empty() =>
+            {
+                Ok(Region::from(Rect {
+                    min_x,
+                    max_x,
+                    min_y,
+                    max_y,
+                }))
+            }
+            _ if edges.iter().all(Option::is_some) => {
+                Err("expected either a rectangle or a region, not both")
+            }
+            _ => Err("expected `min-x`, `max-x`, `min-y` and `max-y`, or a region"),
+        }
+    }
+}
+
+impl From<Region> for RegionRepr {
+    fn from(region: Region) -> Self {
+        let shape = ShapeRepr::from(region.shape);
+        RegionRepr {
+            rect: shape.rect,
+            polygon: shape.polygon,
+            circle: shape.circle,
+            ellipse: shape.ellipse,
+            holes: region.holes,
+        }
+    }
+}
+
+impl From<Shape> for Region {
+    fn from(shape: Shape) -> Self {
+        Region::new(shape)
+    }
+}
+
+impl From<Rect> for Region {
+    fn from(rect: Rect) -> Self {
+        Region::new(Shape::Rect(rect.into()))
+    }
+}
+
+impl From<Rect<Length>> for Region {
+    fn from(rect: Rect<Length>) -> Self {
+        Region::new(Shape::Rect(rect))
+    }
+}
+
+impl ResolveUnits for Circle {
+    fn resolve_units(&mut self, units: &mut Conversion) {
+        self.cx.resolve_units(units);
+        self.cy.resolve_units(units);
+        self.radius.resolve_units(units);
+    }
+}
+
+impl ResolveUnits for Ellipse {
+    fn resolve_units(&mut self, units: &mut Conversion) {
+        self.cx.resolve_units(units);
+        self.cy.resolve_units(units);
+        self.rx.resolve_units(units);
+        self.ry.resolve_units(units);
+    }
+}
+
+impl ResolveUnits for Shape {
+    fn resolve_units(&mut self, units: &mut Conversion) {
+        match self {
+            Shape::Rect(rect) => rect.resolve_units(units),
+            Shape::Polygon(points) => points.resolve_units(units),
+            Shape::Circle(circle) => circle.resolve_units(units),
+            Shape::Ellipse(ellipse) => ellipse.resolve_units(units),
+        }
+    }
+}
+
+impl ResolveUnits for Region {
+    fn resolve_units(&mut self, units: &mut Conversion) {
+        self.shape.resolve_units(units);
+        self.holes.resolve_units(units);
+    }
+}
+
+impl Circle {
+    pub fn new(cx: f64, cy: f64, radius: f64) -> Self {
+        Circle {
+            cx: cx.into(),
+            cy: cy.into(),
+            radius: radius.into(),
+        }
+    }
+
+    pub fn center(&self) -> Point {
+        Point::new(self.cx.get(), self.cy.get())
+    }
+}
+
+impl Ellipse {
+    pub fn new(cx: f64, cy: f64, rx: f64, ry: f64) -> Self {
+        Ellipse {
+            cx: cx.into(),
+            cy: cy.into(),
+            rx: rx.into(),
+            ry: ry.into(),
+            theta: 0.0,
+        }
+    }
+
+    pub fn with_theta(mut self, theta: f64) -> Self {
+        self.theta = theta;
+        self
+    }
+
+    pub fn center(&self) -> Point {
+        Point::new(self.cx.get(), self.cy.get())
+    }
+
+    /// Maps `point` to coordinates in which the ellipse is the unit circle around the origin.
+    fn unit_coordinates(&self, point: Point) -> Point {
+        let (cos, sin) = cos_sin_degrees(self.theta);
+        let offset = point - self.center();
+        Point::new(
+            (offset.x * cos + offset.y * sin) / self.rx.get(),
+            (offset.y * cos - offset.x * sin) / self.ry.get(),
+        )
+    }
+}
+
+impl Shape {
+    /// Whether `point` lies inside the shape or on its edge. Polygons use the even-odd rule.
+    pub fn contains(&self, point: Point) -> bool {
+        match self {
+            Shape::Rect(rect) => rect.get().contains_point(point),
+            Shape::Polygon(points) => {
+                let mut inside = false;
+                for (i, a) in points.iter().enumerate() {
+                    let (a, b) = (a.get(), points[(i + 1) % points.len()].get());
+                    if (a.y > point.y) != (b.y > point.y) {
+                        let x = a.x + (point.y - a.y) / (b.y - a.y) * (b.x - a.x);
+                        if point.x < x {
+                            inside = !inside;
+                        }
+                    }
+                }
+                inside
+            }
+            Shape::Circle(circle) => circle.center().distance(point) <= circle.radius.get(),
+            Shape::Ellipse(ellipse) => ellipse.unit_coordinates(point).length() <= 1.0,
+        }
+    }
+
+    /// The smallest rectangle containing the shape.
+    pub fn bounding_box(&self) -> Rect {
+        match self {
+            Shape::Rect(rect) => rect.get(),
+            Shape::Polygon(points) => points.iter().map(|p| p.get()).fold(
+                Rect::new(
+                    f64::INFINITY,
+                    f64::NEG_INFINITY,
+                    f64::INFINITY,
+                    f64::NEG_INFINITY,
+                ),
+                |rect, p| {
+                    Rect::new(
+                        rect.min_x.min(p.x),
+                        rect.max_x.max(p.x),
+                        rect.min_y.min(p.y),
+                        rect.max_y.max(p.y),
+                    )
+                },
+            ),
+            Shape::Circle(circle) => {
+                let (center, radius) = (circle.center(), circle.radius.get());
+                Rect::new(
+                    center.x - radius,
+                    center.x + radius,
+                    center.y - radius,
+                    center.y + radius,
+                )
+            }
+            Shape::Ellipse(ellipse) => {
+                let (cos, sin) = cos_sin_degrees(ellipse.theta);
+                let (rx, ry) = (ellipse.rx.get(), ellipse.ry.get());
+                let half_width = (rx * cos).hypot(ry * sin);
+                let half_height = (rx * sin).hypot(ry * cos);
+                let center = ellipse.center();
+                Rect::new(
+                    center.x - half_width,
+                    center.x + half_width,
+                    center.y - half_height,
+                    center.y + half_height,
+                )
+            }
+        }
+    }
+
+    /// The vertices of the shape, with circles and ellipses approximated by polygons with
+    /// `CURVE_SIDES` sides.
+    fn outline(&self) -> Vec<Point> {
+        let curve = |at: &dyn Fn(f64, f64) -> Point| {
+            (0..CURVE_SIDES)
+                .map(|i| {
+                    let angle = (i as f64 * 360.0 / CURVE_SIDES as f64).to_radians();
+                    at(angle.cos(), angle.sin())
+                })
+                .collect()
+        };
+        match self {
+            Shape::Rect(rect) => {
+                let rect = rect.get();
+                vec![
+                    Point::new(rect.min_x, rect.min_y),
+                    Point::new(rect.max_x, rect.min_y),
+                    Point::new(rect.max_x, rect.max_y),
+                    Point::new(rect.min_x, rect.max_y),
+                ]
+            }
+            Shape::Polygon(points) => points.iter().map(|p| p.get()).collect(),
+            Shape::Circle(circle) => {
+                let radius = circle.radius.get();
+                curve(&|cos, sin| circle.center() + Point::new(cos, sin) * radius)
+            }
+            Shape::Ellipse(ellipse) => {
+                let (cos_theta, sin_theta) = cos_sin_degrees(ellipse.theta);
+                let (rx, ry) = (ellipse.rx.get(), ellipse.ry.get());
+                curve(&|cos, sin| {
+                    let (x, y) = (rx * cos, ry * sin);
+                    ellipse.center()
+                        + Point::new(x * cos_theta - y * sin_theta, x * sin_theta + y * cos_theta)
+                })
+            }
+        }
+    }
+
+    /// Returns the ranges of `t` in `0..=1` for which `segment.at(t)` lies inside the shape, in
+    /// increasing order.
+    pub(crate) fn intervals(&self, segment: Segment) -> Vec<(f64, f64)> {
+        let direction = segment.end - segment.start;
+        match self {
+            Shape::Rect(rect) => rect.get().clip_interval(segment).into_iter().collect(),
+            Shape::Polygon(points) => {
+                // cut the segment where it crosses an edge, and keep the pieces inside
+                let mut cuts = vec![0.0, 1.0];
+                for (i, a) in points.iter().enumerate() {
+                    let a = a.get();
+                    let edge = points[(i + 1) % points.len()].get() - a;
+                    let denominator = direction.cross(edge);
+                    if denominator == 0.0 {
+                        continue;
+                    }
+                    let offset = a - segment.start;
+                    let t = offset.cross(edge) / denominator;
+                    let u = offset.cross(direction) / denominator;
+                    if (0.0..=1.0).contains(&t) && (0.0..=1.0).contains(&u) {
+                        cuts.push(t);
+                    }
+                }
+                cuts.sort_by(f64::total_cmp);
+                let mut intervals: Vec<(f64, f64)> = Vec::new();
+                for pair in cuts.windows(2) {
+                    let (t0, t1) = (pair[0], pair[1]);
+                    if t0 >= t1 || !self.contains(segment.at((t0 + t1) / 2.0)) {
+                        continue;
+                    }
+                    match intervals.last_mut() {
+                        Some(last) if last.1 == t0 => last.1 = t1,
+                        _ => intervals.push((t0, t1)),
+                    }
+                }
+                intervals
+            }
+            Shape::Circle(circle) => circle_intervals(
+                segment.start - circle.center(),
+                direction,
+                circle.radius.get(),
+            ),
+            Shape::Ellipse(ellipse) => {
+                // the mapping to the unit circle is affine, so it keeps positions along the segment
+                let start = ellipse.unit_coordinates(segment.start);
+                circle_intervals(start, ellipse.unit_coordinates(segment.end) - start, 1.0)
+            }
+        }
+    }
+
+    /// Returns the angles at which the circle of `arc` crosses the edge of the shape, or the lines
+    /// through the edges of a rectangle. For a polygon, the angles of the ends of the part of each
+    /// edge inside the circle are returned, which may include vertices inside it.
+    fn crossings(&self, arc: Arc) -> Vec<f64> {
+        let angle = |point: Point| {
+            let offset = point - arc.center;
+            offset.y.atan2(offset.x).to_degrees()
+        };
+        match self {
+            Shape::Rect(rect) => {
+                // angles at which the circle crosses the lines through the edges
+                let rect = rect.get();
+                let mut angles = Vec::new();
+                for (edge, center, is_x) in [
+                    (rect.min_x, arc.center.x, true),
+                    (rect.max_x, arc.center.x, true),
+                    (rect.min_y, arc.center.y, false),
+                    (rect.max_y, arc.center.y, false),
+                ] {
+                    let ratio = (edge - center) / arc.radius;
+                    if ratio.abs() >= 1.0 {
+                        continue;
+                    }
+                    if is_x {
+                        let angle = ratio.acos().to_degrees();
+                        angles.extend([angle, -angle]);
+                    } else {
+                        let angle = ratio.asin().to_degrees();
+                        angles.extend([angle, 180.0 - angle]);
+                    }
+                }
+                angles
+            }
+            Shape::Polygon(_) => {
+                let points = self.outline();
+                let mut angles = Vec::new();
+                for (i, &a) in points.iter().enumerate() {
+                    let edge = points[(i + 1) % points.len()] - a;
+                    let from_center = a - arc.center;
+                    for (u0, u1) in circle_intervals(from_center, edge, arc.radius) {
+                        angles.extend([angle(a + edge * u0), angle(a + edge * u1)]);
+                    }
+                }
+                angles
+            }
+            Shape::Circle(circle) => {
+                let between = circle.center() - arc.center;
+                let (distance, radius) = (between.length(), circle.radius.get());
+                if distance == 0.0
+                    || distance >= arc.radius + radius
+                    || distance <= (arc.radius - radius).abs()
+                {
+                    return Vec::new();
+                }
+                // distance along `between` to the chord through both crossings
+                let along = (arc.radius * arc.radius - radius * radius + distance * distance)
+                    / (2.0 * distance);
+                let across = (arc.radius * arc.radius - along * along).sqrt();
+                let unit = between * (1.0 / distance);
+                let normal = Point::new(-unit.y, unit.x);
+                let chord = arc.center + unit * along;
+                vec![
+                    angle(chord + normal * across),
+                    angle(chord - normal * across),
+                ]
+            }
+            Shape::Ellipse(ellipse) => {
+                // find sign changes of the distance from the ellipse numerically
+                const SAMPLES: usize = 720;
+                let outside = |angle: f64| ellipse.unit_coordinates(arc.at(angle)).length() - 1.0;
+                let mut angles = Vec::new();
+                for i in 0..SAMPLES {
+                    let (mut from, mut to) = (
+                        i as f64 * 360.0 / SAMPLES as f64,
+                        (i + 1) as f64 * 360.0 / SAMPLES as f64,
+                    );
+                    if (outside(from) > 0.0) == (outside(to) > 0.0) {
+                        continue;
+                    }
+                    for _ in 0..50 {
+                        let middle = (from + to) / 2.0;
+                        if (outside(middle) > 0.0) == (outside(from) > 0.0) {
+                            from = middle;
+                        } else {
+                            to = middle;
+                        }
+                    }
+                    angles.push((from + to) / 2.0);
+                }
+                angles
+            }
+        }
+    }
+
+    pub(crate) fn validate(&self, field: &str) -> Result<(), GridError> {
+        match self {
+            Shape::Rect(rect) => rect.get().validate(&format!("{}.rect", field)),
+            Shape::Polygon(points) => {
+                let field = format!("{}.polygon", field);
+                if points.len() < 3 {
+                    return Err(GridError::new(
+                        field,
+                        ErrorKind::TooSmall {
+                            min: 3.0,
+                            value: points.len() as f64,
+                        },
+                    ));
+                }
+                for (i, point) in points.iter().enumerate() {
+                    for value in [point.x.get(), point.y.get()] {
+                        finite(value)
+                            .map_err(|kind| GridError::new(format!("{}.{}", field, i), kind))?;
+                    }
+                }
+                Ok(())
+            }
+            Shape::Circle(circle) => {
+                let error =
+                    |name: &str, kind| GridError::new(format!("{}.circle.{}", field, name), kind);
+                finite(circle.cx.get()).map_err(|kind| error("cx", kind))?;
+                finite(circle.cy.get()).map_err(|kind| error("cy", kind))?;
+                positive(circle.radius.get()).map_err(|kind| error("radius", kind))?;
+                Ok(())
+            }
+            Shape::Ellipse(ellipse) => {
+                let error =
+                    |name: &str, kind| GridError::new(format!("{}.ellipse.{}", field, name), kind);
+                finite(ellipse.cx.get()).map_err(|kind| error("cx", kind))?;
+                finite(ellipse.cy.get()).map_err(|kind| error("cy", kind))?;
+                positive(ellipse.rx.get()).map_err(|kind| error("rx", kind))?;
+                positive(ellipse.ry.get()).map_err(|kind| error("ry", kind))?;
+                finite(ellipse.theta).map_err(|kind| error("theta", kind))?;
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Number of sides of the polygons which approximate circles and ellipses where only polygons
+/// will do.
+const CURVE_SIDES: usize = 72;
+
+/// Returns the range of `t` in `0..=1` for which `start + direction * t` lies within `radius` of
+/// the origin, if any.
+fn circle_intervals(start: Point, direction: Point, radius: f64) -> Vec<(f64, f64)> {
+    let a = direction.dot(direction);
+    let b = 2.0 * start.dot(direction);
+    let c = start.dot(start) - radius * radius;
+    let discriminant = b * b - 4.0 * a * c;
+    if a == 0.0 || discriminant <= 0.0 {
+        return Vec::new();
+    }
+    let root = discriminant.sqrt();
+    let t0 = ((-b - root) / (2.0 * a)).max(0.0);
+    let t1 = ((-b + root) / (2.0 * a)).min(1.0);
+    if t0 < t1 {
+        vec![(t0, t1)]
+    } else {
+        Vec::new()
+    }
+}
+
+impl Region {
+    /// The whole of `shape`, without holes.
+    pub fn new(shape: Shape) -> Self {
+        Region {
+            shape,
+            holes: Vec::new(),
+        }
+    }
+
+    pub fn with_hole(mut self, hole: Shape) -> Self {
+        self.holes.push(hole);
+        self
+    }
+
+    /// The region as a rectangle, if that is all it is.
+    pub fn as_rect(&self) -> Option<Rect> {
+        match self.shape {
+            Shape::Rect(rect) if self.holes.is_empty() => Some(rect.get()),
+            _ => None,
+        }
+    }
+
+    /// Whether `point` lies inside the shape and outside all holes.
+    pub fn contains(&self, point: Point) -> bool {
+        self.shape.contains(point) && !self.holes.iter().any(|hole| hole.contains(point))
+    }
+
+    /// Returns the parts of `segment` which lie inside the region, in order along it.
+    pub fn clip_segment(&self, segment: Segment) -> Vec<Segment> {
+        let mut intervals = self.shape.intervals(segment);
+        for hole in &self.holes {
+            for (start, end) in hole.intervals(segment) {
+                intervals = intervals
+                    .into_iter()
+                    .flat_map(|(t0, t1)| [(t0, t1.min(start)), (t0.max(end), t1)])
+                    .filter(|(t0, t1)| t0 < t1)
+                    .collect();
+            }
+        }
+        intervals
+            .into_iter()
+            .map(|(t0, t1)| Segment::new(segment.at(t0), segment.at(t1)))
+            .collect()
+    }
+
+    /// The same region with circles and ellipses approximated by polygons with `CURVE_SIDES`
+    /// sides, and rectangles turned into polygons.
+    pub(crate) fn polygonal(&self) -> Region {
+        let polygon =
+            |shape: &Shape| Shape::Polygon(shape.outline().into_iter().map(Point::into).collect());
+        Region {
+            shape: polygon(&self.shape),
+            holes: self.holes.iter().map(polygon).collect(),
+        }
+    }
+
+    /// The edges of the shape and holes, which must all be polygons; see `polygonal`.
+    pub(crate) fn edges(&self) -> impl Iterator<Item = Segment> + '_ {
+        std::iter::once(&self.shape)
+            .chain(&self.holes)
+            .flat_map(|shape| {
+                let points = shape.outline();
+                (0..points.len())
+                    .map(move |i| Segment::new(points[i], points[(i + 1) % points.len()]))
+            })
+    }
+
+    /// Returns the parts of `arc` which lie inside the region, in order along the arc. A whole
+    /// circle is returned as is if it lies entirely inside.
+    pub fn clip_arc(&self, arc: Arc) -> Vec<Arc> {
+        let mut cuts = vec![0.0, arc.sweep];
+        for shape in std::iter::once(&self.shape).chain(&self.holes) {
+            for angle in shape.crossings(arc) {
+                let relative = (angle - arc.start).rem_euclid(360.0);
+                if relative < arc.sweep {
+                    cuts.push(relative);
+                }
+            }
+        }
+        cuts.sort_by(f64::total_cmp);
+
+        // intervals of the arc inside the region, relative to its start
+        let mut inside: Vec<(f64, f64)> = Vec::new();
+        for pair in cuts.windows(2) {
+            let (from, to) = (pair[0], pair[1]);
+            if from == to || !self.contains(arc.at(arc.start + (from + to) / 2.0)) {
+                continue;
+            }
+            match inside.last_mut() {
+                Some(last) if last.1 == from => last.1 = to,
+                _ => inside.push((from, to)),
+            }
+        }
+        // rejoin the intervals either side of the start of a circle
+        if arc.is_circle() && inside.len() > 1 && inside[0].0 == 0.0 {
+            let last = inside.len() - 1;
+            if inside[last].1 == arc.sweep {
+                let (from, _) = inside.pop().expect("checked length");
+                inside[0].0 = from - arc.sweep;
+            }
+        }
+        inside
+            .into_iter()
+            .map(|(from, to)| Arc::new(arc.center, arc.radius, arc.start + from, to - from))
+            .collect()
+    }
+
+    pub(crate) fn validate(&self, field: &str) -> Result<(), GridError> {
+        self.shape.validate(field)?;
+        for (i, hole) in self.holes.iter().enumerate() {
+            hole.validate(&format!("{}.holes.{}", field, i))?;
+        }
+        Ok(())
+    }
+}
+
+/// Serializes an optional region, writing plain rectangles as a `Rect`, and deserializes either a
+/// `Rect` or a region.
+pub(crate) mod rect_or_region {
+    use serde::{Deserializer, Serialize, Serializer};
+
+    use super::{ClipRepr, Converted, Region};
+
+    pub fn serialize<S: Serializer>(
+        region: &Option<Region>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        match region.as_ref().map(|region| (region, region.as_rect())) {
+            Some((_, Some(rect))) => rect.serialize(serializer),
+            Some((region, None)) => region.serialize(serializer),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<Region>, D::Error> {
+        Converted::<ClipRepr, Region>::deserialize(deserializer).map(Some)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn square_with_hole() -> Region {
+        Region::from(Rect::new(0.0, 100.0, 0.0, 100.0))
+            .with_hole(Shape::Circle(Circle::new(50.0, 50.0, 10.0)))
+            .with_hole(Shape::Rect(Rect::new(70.0, 80.0, 0.0, 100.0).into()))
+    }
+
+    #[test]
+    fn clips_segments_around_holes() {
+        let pieces = square_with_hole().clip_segment(Segment::new((-10.0, 50.0), (110.0, 50.0)));
+        assert_eq!(
+            pieces,
+            vec![
+                Segment::new((0.0, 50.0), (40.0, 50.0)),
+                Segment::new((60.0, 50.0), (70.0, 50.0)),
+                Segment::new((80.0, 50.0), (100.0, 50.0)),
+            ]
+        );
+        // missing the circle, but still crossing the rectangular hole
+        let pieces = square_with_hole().clip_segment(Segment::new((0.0, 10.0), (100.0, 10.0)));
+        assert_eq!(pieces.len(), 2);
+        // entirely inside a hole
+        let segment = Segment::new((72.0, 10.0), (78.0, 90.0));
+        assert!(square_with_hole().clip_segment(segment).is_empty());
+    }
+
+    #[test]
+    fn clips_segments_to_concave_polygons() {
+        // a U shape, open at the top
+        let points = [
+            (0.0, 0.0),
+            (30.0, 0.0),
+            (30.0, 30.0),
+            (20.0, 30.0),
+            (20.0, 10.0),
+        ]
+        .into_iter()
+        .chain([(10.0, 10.0), (10.0, 30.0), (0.0, 30.0)])
+        .map(|(x, y)| Point::new(x, y).into())
+        .collect();
+        let region = Region::new(Shape::Polygon(points));
+        let pieces = region.clip_segment(Segment::new((-5.0, 20.0), (35.0, 20.0)));
+        assert_eq!(
+            pieces,
+            vec![
+                Segment::new((0.0, 20.0), (10.0, 20.0)),
+                Segment::new((20.0, 20.0), (30.0, 20.0)),
+            ]
+        );
+        let pieces = region.clip_segment(Segment::new((-5.0, 5.0), (35.0, 5.0)));
+        assert_eq!(pieces, vec![Segment::new((0.0, 5.0), (30.0, 5.0))]);
+    }
+
+    #[test]
+    fn clips_arcs_like_rectangles() {
+        let rect = Rect::new(0.0, 100.0, 0.0, 100.0);
+        let arc = Arc::circle(Point::new(0.0, 0.0), 50.0);
+        let arcs = rect.clip_arc(arc);
+        assert_eq!(arcs, vec![Arc::new(Point::new(0.0, 0.0), 50.0, 0.0, 90.0)]);
+        assert_eq!(Region::from(rect).clip_arc(arc), arcs);
+        // a whole circle inside is kept as is
+        let inside = Arc::circle(Point::new(50.0, 50.0), 10.0);
+        assert_eq!(rect.clip_arc(inside), vec![inside]);
+        // the hole splits the circle around it
+        let around = Arc::circle(Point::new(50.0, 50.0), 20.0);
+        let region =
+            Region::from(rect).with_hole(Shape::Rect(Rect::new(0.0, 50.0, 0.0, 100.0).into()));
+        assert_eq!(
+            region.clip_arc(around),
+            vec![Arc::new(Point::new(50.0, 50.0), 20.0, -90.0, 180.0)]
+        );
+    }
+}
@@ -0,0 +1,299 @@
+use std::{fmt::Write as _, io};
+
+use super::{GroupInfo, GroupKind, Renderer, Style};
+use crate::{Arc, Color, Point, Rect, Region, Segment, Unit};
+
+/// Renders grids as an AutoCAD R12 DXF drawing, with each grid on its own layer. Layers are named
+/// after their grid, or `GRID-<index>` or `MARKERS-<index>` if unnamed, with `-MAJOR-<n>`
+/// appended for the `n`th tier of major lines, and filled faces are on the `FILL` layer. Names
+/// are uppercased, with characters R12 doesn't allow replaced by `_`, and groups with the same
+/// name share a layer. Layers are colored with the AutoCAD color index closest to the stroke of
+/// their first group, and later groups with another stroke color their entities instead. Fully
+/// transparent fills are left out. The y-axis is flipped, since DXF coordinates point up.
+#[derive(Debug, Default)]
+pub struct DxfRenderer {
+    bounds: Rect,
+    default_color: u8,
+    /// Name and color index of each layer
+    layers: Vec<(String, u8)>,
+    /// Index of the layer of the current group, and its color if it differs from the layer's
+    group: Option<(usize, Option<u8>)>,
+    entities: String,
+    output: String,
+}
+
+impl DxfRenderer {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Name of the layer of the current group.
+    fn layer(&self, entity: &str) -> &str {
+        let (layer, _) = self
+            .group
+            .unwrap_or_else(|| panic!("{} outside of group", entity));
+        &self.layers[layer].0
+    }
+
+    /// Starts an entity of type `kind` on the layer of the current group, in the group's color.
+    fn begin_entity(&mut self, kind: &str) {
+        let layer = self.layer(kind).to_owned();
+        let _ = write!(self.entities, "0\n{}\n8\n{}\n", kind, layer);
+        if let Some((_, Some(color))) = self.group {
+            let _ = write!(self.entities, "62\n{}\n", color);
+        }
+    }
+
+    fn circle(&mut self, center: Point, radius: f64) {
+        self.begin_entity("CIRCLE");
+        let _ = write!(
+            self.entities,
+            "10\n{}\n20\n{}\n30\n0\n40\n{}\n",
+            center.x, -center.y, radius,
+        );
+    }
+}
+
+impl Renderer for DxfRenderer {
+    fn begin_document(
+        &mut self,
+        bounds: Rect,
+        _area: &Region,
+        _units: Option<Unit>,
+        style: &Style,
+    ) {
+        self.bounds = bounds;
+        self.default_color = style.stroke.and_then(Color::parse).map_or(7, aci);
+        self.layers.clear();
+        self.group = None;
+        self.entities.clear();
+    }
+
+    fn begin_group(&mut self, group: &GroupInfo) {
+        let mut name = match (group.name, group.kind) {
+            (Some(name), _) => name.to_owned(),
+            (None, GroupKind::Grid { grid, .. }) => format!("grid-{}", grid),
+            (None, GroupKind::Markers(markers)) => format!("markers-{}", markers),
+            (None, GroupKind::Fill) => "fill".into(),
+        };
+        if let GroupKind::Grid {
+            tier: Some(tier), ..
+        } = group.kind
+        {
+            let _ = write!(name, "-major-{}", tier + 1);
+        }
+        let color = group
+            .style
+            .stroke
+            .and_then(Color::parse)
+            .map_or(self.default_color, aci);
+        let name = layer_name(&name);
+        let layer = match self.layers.iter().position(|(layer, _)| *layer == name) {
+            Some(layer) => layer,
+            None => {
+                self.layers.push((name, color));
+                self.layers.len() - 1
+            }
+        };
+        let own_color = Some(color).filter(|&color| color != self.layers[layer].1);
+        self.group = Some((layer, own_color));
+    }
+
+    fn segment(&mut self, segment: &Segment) {
+        self.begin_entity("LINE");
+        let _ = write!(
+            self.entities,
+            "10\n{}\n20\n{}\n30\n0\n11\n{}\n21\n{}\n31\n0\n",
+            segment.start.x, -segment.start.y, segment.end.x, -segment.end.y,
+        );
+    }
+
+    fn arc(&mut self, arc: &Arc) {
+        if arc.is_circle() {
+            self.circle(arc.center, arc.radius);
+            return;
+        }
+        self.begin_entity("ARC");
+        // flipping the y-axis turns clockwise angles into counterclockwise ones, which DXF uses
+        let _ = write!(
+            self.entities,
+            "10\n{}\n20\n{}\n30\n0\n40\n{}\n50\n{}\n51\n{}\n",
+            arc.center.x,
+            -arc.center.y,
+            arc.radius,
+            (-arc.start - arc.sweep).rem_euclid(360.0),
+            (-arc.start).rem_euclid(360.0),
+        );
+    }
+
+    fn polygon(&mut self, points: &[Point]) {
+        // a closed polyline
+        self.begin_entity("POLYLINE");
+        self.entities
+            .push_str("66\n1\n10\n0\n20\n0\n30\n0\n70\n1\n");
+        let layer = self.layer("polygon").to_owned();
+        for p in points {
+            let _ = write!(
+                self.entities,
+                "0\nVERTEX\n8\n{}\n10\n{}\n20\n{}\n30\n0\n",
+                layer, p.x, -p.y,
+            );
+        }
+        let _ = write!(self.entities, "0\nSEQEND\n8\n{}\n", layer);
+    }
+
+    fn dot(&mut self, center: Point, radius: f64) {
+        self.circle(center, radius);
+    }
+
+    fn fill(&mut self, points: &[Point], color: &str) {
+        let layer = self.layer("fill").to_owned();
+        let color = match Color::parse(color) {
+            // R12 has no transparency, so fills which would be invisible are left out
+            Some(color) if color.a == 0 => return,
+            color => color.map_or(self.default_color, aci),
+        };
+        // R12 has no hatches, so fill with triangles fanning out from the first vertex, which
+        // covers convex polygons
+        for pair in points.windows(2).skip(1) {
+            let (a, b, c) = (points[0], pair[0], pair[1]);
+            let _ = write!(
+                self.entities,
+                "0\nSOLID\n8\n{}\n62\n{}\n10\n{}\n20\n{}\n30\n0\n11\n{}\n21\n{}\n31\n0\n\
+                 12\n{}\n22\n{}\n32\n0\n13\n{}\n23\n{}\n33\n0\n",
+                layer, color, a.x, -a.y, b.x, -b.y, c.x, -c.y, c.x, -c.y,
+            );
+        }
+    }
+
+    fn end_group(&mut self) {
+        self.group = None;
+    }
+
+    fn end_document(&mut self) {
+        let out = &mut self.output;
+        out.clear();
+        let _ = write!(
+            out,
+            "0\nSECTION\n2\nHEADER\n9\n$ACADVER\n1\nAC1009\n\
+             9\n$EXTMIN\n10\n{}\n20\n{}\n9\n$EXTMAX\n10\n{}\n20\n{}\n0\nENDSEC\n",
+            self.bounds.min_x, -self.bounds.max_y, self.bounds.max_x, -self.bounds.min_y,
+        );
+        out.push_str(
+            "0\nSECTION\n2\nTABLES\n\
+             0\nTABLE\n2\nLTYPE\n70\n1\n\
+             0\nLTYPE\n2\nCONTINUOUS\n70\n0\n3\nSolid line\n72\n65\n73\n0\n40\n0.0\n\
+             0\nENDTAB\n",
+        );
+        let _ = write!(out, "0\nTABLE\n2\nLAYER\n70\n{}\n", self.layers.len());
+        for (name, color) in &self.layers {
+            let _ = write!(
+                out,
+                "0\nLAYER\n2\n{}\n70\n0\n62\n{}\n6\nCONTINUOUS\n",
+                name, color
+            );
+        }
+        out.push_str("0\nENDTAB\n0\nENDSEC\n0\nSECTION\n2\nENTITIES\n");
+        out.push_str(&self.entities);
+        out.push_str("0\nENDSEC\n0\nEOF\n");
+    }
+
+    fn write(&self, out: &mut dyn io::Write) -> io::Result<()> {
+        out.write_all(self.output.as_bytes())
+    }
+}
+
+/// Longest layer name R12 allows.
+const MAX_LAYER_NAME: usize = 31;
+
+/// Uppercases `name` and replaces characters which R12 doesn't allow in layer names.
+fn layer_name(name: &str) -> String {
+    name.chars()
+        .take(MAX_LAYER_NAME)
+        .map(|c| match c.to_ascii_uppercase() {
+            c @ ('A'..='Z' | '0'..='9' | '_' | '$' | '-') => c,
+            _ => '_',
+        })
+        .collect()
+}
+
+/// Returns the AutoCAD color index closest to `color`, ignoring its alpha.
+fn aci(color: Color) -> u8 {
+    let distance = |(r, g, b): (u8, u8, u8)| {
+        let d = |x: u8, y: u8| (x as i32 - y as i32).pow(2);
+        d(r, color.r) + d(g, color.g) + d(b, color.b)
+    };
+    (1..=255)
+        .min_by_key(|&index| distance(aci_rgb(index)))
+        .unwrap_or(7)
+}
+
+/// Approximate RGB value of an AutoCAD color index, in the range 1..=255.
+fn aci_rgb(index: u8) -> (u8, u8, u8) {
+    match index {
+        1 => (255, 0, 0),
+        2 => (255, 255, 0),
+        3 => (0, 255, 0),
+        4 => (0, 255, 255),
+        5 => (0, 0, 255),
+        6 => (255, 0, 255),
+        // white on a dark background, black on a light one; treated as black here
+        7 => (0, 0, 0),
+        8 => (128, 128, 128),
+        9 => (192, 192, 192),
+        10..=249 => {
+            // 24 hues, each with 5 shades at full and half saturation
+            let hue = (index - 10) / 10;
+            let shade = (index - 10) % 10;
+            let value = [1.0, 0.8, 0.6, 0.5, 0.3][shade as usize / 2];
+            let saturation = [1.0, 0.5][shade as usize % 2];
+            hsv_to_rgb(hue as f64 * 15.0, saturation, value)
+        }
+        _ => {
+            let level = (51 + 41 * (index as u32 - 250)).min(255) as u8;
+            (level, level, level)
+        }
+    }
+}
+
+fn hsv_to_rgb(hue: f64, saturation: f64, value: f64) -> (u8, u8, u8) {
+    let channel = |n: f64| {
+        let k = (n + hue / 60.0) % 6.0;
+        let c = value - value * saturation * k.min(4.0 - k).clamp(0.0, 1.0);
+        (c * 255.0).round() as u8
+    };
+    (channel(5.0), channel(3.0), channel(1.0))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn leaves_out_transparent_fills() {
+        let mut renderer = DxfRenderer::new();
+        let bounds = Rect::new(0.0, 10.0, 0.0, 10.0);
+        renderer.begin_document(bounds, &bounds.into(), None, &Style::default());
+        renderer.begin_group(&GroupInfo {
+            kind: GroupKind::Fill,
+            name: None,
+            style: Style::default(),
+        });
+        let square = [
+            Point::new(0.0, 0.0),
+            Point::new(10.0, 0.0),
+            Point::new(10.0, 10.0),
+            Point::new(0.0, 10.0),
+        ];
+        for color in ["transparent", "none", "#ff000000", "rgba(0, 0, 255, 0)"] {
+            renderer.fill(&square, color);
+        }
+        assert!(!renderer.entities.contains("SOLID"));
+        renderer.fill(&square, "red");
+        // two triangles, in ACI red
+        assert_eq!(
+            renderer.entities.matches("SOLID\n8\nFILL\n62\n1\n").count(),
+            2
+        );
+    }
+}
@@ -0,0 +1,98 @@
+use std::io;
+
+use crate::{Arc, Point, Rect, Region, Segment, Unit};
+
+mod dxf;
+mod pdf;
+mod plotter;
+mod png;
+mod svg;
+
+pub use self::{
+    dxf::DxfRenderer,
+    pdf::PdfRenderer,
+    plotter::{PlotterRenderer, PlotterSettings},
+    png::{PngRenderer, Resolution},
+    svg::SvgRenderer,
+};
+
+/// An output format for rendered grids.
+///
+/// `GridCollection::render` calls `begin_document` once, then `begin_group`, `segment`, `arc`,
+/// `polygon`, `dot` or `fill` for each element of the group, and `end_group` for each group in
+/// turn, and finally `end_document`. The rendered document can then be written out with `write`.
+pub trait Renderer {
+    /// Starts a document spanning `bounds`, of which only `area` will be drawn on. Coordinates are
+    /// in `units` if the document has a physical size. `style` holds the defaults for all grids.
+    fn begin_document(&mut self, bounds: Rect, area: &Region, units: Option<Unit>, style: &Style);
+
+    /// Starts a group of segments, arcs, polygons, dots or fills, from a single grid or set of
+    /// markers, which share a style.
+    fn begin_group(&mut self, group: &GroupInfo);
+
+    fn segment(&mut self, segment: &Segment);
+
+    /// Draws an arc or a whole circle, unfilled.
+    fn arc(&mut self, arc: &Arc);
+
+    /// Draws the outline of the closed polygon with vertices `points`.
+    fn polygon(&mut self, points: &[Point]);
+
+    /// Draws a filled circle, in the stroke color of the group.
+    fn dot(&mut self, center: Point, radius: f64);
+
+    /// Fills the polygon with vertices `points` with `color`, a CSS color, without outlining it.
+    fn fill(&mut self, points: &[Point], color: &str);
+
+    fn end_group(&mut self);
+
+    fn end_document(&mut self);
+
+    /// Writes the rendered document to `out`.
+    fn write(&self, out: &mut dyn io::Write) -> io::Result<()>;
+}
+
+/// Describes a group of segments.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct GroupInfo<'a> {
+    pub kind: GroupKind,
+    /// Name of the grid or set of markers, if it has one
+    pub name: Option<&'a str>,
+    /// Unset fields are inherited from the document
+    pub style: Style<'a>,
+}
+
+/// What a group of segments is drawing.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GroupKind {
+    /// Lines, circles or other shapes of a grid
+    Grid {
+        /// Index of the grid in the collection
+        grid: usize,
+        /// For major lines, the index of their tier in `Grid::major`
+        tier: Option<usize>,
+    },
+    /// Markers at intersections, with the index of the set of markers in the collection
+    Markers(usize),
+    /// Filled faces between the lines of the grids
+    Fill,
+}
+
+/// Stroke styling for a document or group.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Style<'a> {
+    /// Stroke color, as a CSS color
+    pub stroke: Option<&'a str>,
+    /// Stroke width
+    pub stroke_width: Option<f64>,
+}
+
+impl<'a> Style<'a> {
+    /// Fills in unset fields from `fallback`.
+    pub fn or(self, fallback: Style<'a>) -> Style<'a> {
+        Style {
+            stroke: self.stroke.or(fallback.stroke),
+            stroke_width: self.stroke_width.or(fallback.stroke_width),
+        }
+    }
+}
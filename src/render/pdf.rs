@@ -0,0 +1,205 @@
+use std::io;
+
+use pdf_writer::{Content, Finish, Name, Pdf, Ref};
+
+use super::{GroupInfo, Renderer, Style};
+use crate::{Arc, Color, PageSize, Point, Rect, Region, Segment, Unit};
+
+/// Renders grids as a single-page vector PDF, centered on the page. Collections with physical
+/// units are drawn at their actual size, and others are scaled to fit the page, preserving their
+/// aspect ratio.
+pub struct PdfRenderer {
+    page: PageSize,
+    content: Content,
+    /// Whether there are lines in the current path which haven't been stroked yet
+    pending_stroke: bool,
+    /// Distinct stroke opacities used, each of which needs its own graphics state
+    alphas: Vec<u8>,
+    output: Vec<u8>,
+}
+
+impl PdfRenderer {
+    pub fn new(page: PageSize) -> Self {
+        PdfRenderer {
+            page,
+            content: Content::new(),
+            pending_stroke: false,
+            alphas: Vec::new(),
+            output: Vec::new(),
+        }
+    }
+
+    /// Adds `arc` to the current path.
+    fn path_arc(&mut self, arc: &Arc) {
+        let start = arc.start_point();
+        self.content.move_to(start.x as f32, start.y as f32);
+        for [_, c1, c2, end] in arc.beziers() {
+            self.content.cubic_to(
+                c1.x as f32,
+                c1.y as f32,
+                c2.x as f32,
+                c2.y as f32,
+                end.x as f32,
+                end.y as f32,
+            );
+        }
+    }
+
+    fn stroke_pending(&mut self) {
+        if self.pending_stroke {
+            self.content.stroke();
+            self.pending_stroke = false;
+        }
+    }
+
+    /// Sets the stroke and fill color.
+    fn set_color(&mut self, color: Color) {
+        let [r, g, b] = color.rgb_f32();
+        self.content.set_stroke_rgb(r, g, b).set_fill_rgb(r, g, b);
+        let state = self.alpha_state(color.a);
+        self.content.set_parameters(Name(state.as_bytes()));
+    }
+
+    fn alpha_state(&mut self, alpha: u8) -> String {
+        let index = match self.alphas.iter().position(|&a| a == alpha) {
+            Some(index) => index,
+            None => {
+                self.alphas.push(alpha);
+                self.alphas.len() - 1
+            }
+        };
+        format!("GS{}", index)
+    }
+}
+
+impl Renderer for PdfRenderer {
+    fn begin_document(&mut self, bounds: Rect, _area: &Region, units: Option<Unit>, style: &Style) {
+        let default_stroke = style.stroke.and_then(Color::parse).unwrap_or(Color::BLACK);
+        self.content = Content::new();
+        self.alphas.clear();
+
+        let scale = match units {
+            Some(units) => units.convert(1.0, Unit::Pt),
+            None => (self.page.width / bounds.width()).min(self.page.height / bounds.height()),
+        };
+        let x_margin = (self.page.width - bounds.width() * scale) / 2.0;
+        let y_margin = (self.page.height - bounds.height() * scale) / 2.0;
+        // flip the y-axis, since PDF coordinates point up
+        self.content.transform([
+            scale as f32,
+            0.0,
+            0.0,
+            -scale as f32,
+            (x_margin - bounds.min_x * scale) as f32,
+            (self.page.height - y_margin + bounds.min_y * scale) as f32,
+        ]);
+        self.set_color(default_stroke);
+        self.content
+            .set_line_width(style.stroke_width.unwrap_or(1.0) as f32);
+    }
+
+    fn begin_group(&mut self, group: &GroupInfo) {
+        let style = &group.style;
+        self.content.save_state();
+        if let Some(stroke) = style.stroke {
+            self.set_color(Color::parse(stroke).unwrap_or(Color::BLACK));
+        }
+        if let Some(width) = style.stroke_width {
+            self.content.set_line_width(width as f32);
+        }
+    }
+
+    fn segment(&mut self, segment: &Segment) {
+        self.content
+            .move_to(segment.start.x as f32, segment.start.y as f32)
+            .line_to(segment.end.x as f32, segment.end.y as f32);
+        self.pending_stroke = true;
+    }
+
+    fn arc(&mut self, arc: &Arc) {
+        self.path_arc(arc);
+        self.pending_stroke = true;
+    }
+
+    fn polygon(&mut self, points: &[Point]) {
+        if let [first, rest @ ..] = points {
+            self.content.move_to(first.x as f32, first.y as f32);
+            for p in rest {
+                self.content.line_to(p.x as f32, p.y as f32);
+            }
+            self.content.close_path();
+            self.pending_stroke = true;
+        }
+    }
+
+    fn dot(&mut self, center: Point, radius: f64) {
+        // filling would consume any lines in the current path
+        self.stroke_pending();
+        self.path_arc(&Arc::circle(center, radius));
+        self.content.close_path().fill_nonzero();
+    }
+
+    fn fill(&mut self, points: &[Point], color: &str) {
+        if let [first, rest @ ..] = points {
+            // filling would consume any lines in the current path
+            self.stroke_pending();
+            let color = Color::parse(color).unwrap_or(Color::BLACK);
+            let [r, g, b] = color.rgb_f32();
+            self.content.set_fill_rgb(r, g, b);
+            let state = self.alpha_state(color.a);
+            self.content.set_parameters(Name(state.as_bytes()));
+            self.content.move_to(first.x as f32, first.y as f32);
+            for p in rest {
+                self.content.line_to(p.x as f32, p.y as f32);
+            }
+            self.content.close_path().fill_nonzero();
+        }
+    }
+
+    fn end_group(&mut self) {
+        self.stroke_pending();
+        self.content.restore_state();
+    }
+
+    fn end_document(&mut self) {
+        let catalog_id = Ref::new(1);
+        let page_tree_id = Ref::new(2);
+        let page_id = Ref::new(3);
+        let content_id = Ref::new(4);
+        let first_state_id = 5;
+
+        let mut pdf = Pdf::new();
+        pdf.catalog(catalog_id).pages(page_tree_id);
+        pdf.pages(page_tree_id).kids([page_id]).count(1);
+        let mut page = pdf.page(page_id);
+        page.media_box(pdf_writer::Rect::new(
+            0.0,
+            0.0,
+            self.page.width as f32,
+            self.page.height as f32,
+        ));
+        page.parent(page_tree_id);
+        page.contents(content_id);
+        let mut resources = page.resources();
+        let mut states = resources.ext_g_states();
+        for i in 0..self.alphas.len() {
+            let name = format!("GS{}", i);
+            states.pair(Name(name.as_bytes()), Ref::new(first_state_id + i as i32));
+        }
+        states.finish();
+        resources.finish();
+        page.finish();
+        for (i, &alpha) in self.alphas.iter().enumerate() {
+            pdf.ext_graphics(Ref::new(first_state_id + i as i32))
+                .stroking_alpha(alpha as f32 / 255.0)
+                .non_stroking_alpha(alpha as f32 / 255.0);
+        }
+        let content = std::mem::replace(&mut self.content, Content::new());
+        pdf.stream(content_id, &content.finish());
+        self.output = pdf.finish();
+    }
+
+    fn write(&self, out: &mut dyn io::Write) -> io::Result<()> {
+        out.write_all(&self.output)
+    }
+}
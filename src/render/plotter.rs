@@ -0,0 +1,321 @@
+use std::{collections::BTreeMap, fmt::Write as _, io};
+
+use serde::{Deserialize, Serialize};
+
+use super::{GroupInfo, Renderer, Style};
+use crate::{
+    error::{color, positive, ErrorKind, GridError},
+    optimize::{optimize, PathStats, PenStrokes},
+    Arc, Color, Point, Rect, Region, Segment, Unit,
+};
+
+/// Settings for pen plotter output.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(default, rename_all = "kebab-case", deny_unknown_fields)]
+pub struct PlotterSettings {
+    /// Millimeters on paper per unit of the bounds. Defaults to the size of the collection's units
+    /// if it has them, or else 1.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub scale: Option<f64>,
+    /// Pen number for each stroke color. Colors which aren't listed use pen 1.
+    pub pens: BTreeMap<String, u32>,
+    /// G-code to raise the pen
+    pub pen_up: String,
+    /// G-code to lower the pen
+    pub pen_down: String,
+    /// G-code to switch pens, with `{pen}` replaced by the pen number. It is also written before
+    /// the first stroke, to select its pen.
+    pub pen_change: String,
+    /// G-code feed rate while drawing, in mm/min
+    pub feed_rate: f64,
+    /// G-code feed rate while the pen is raised, in mm/min. Rapid moves are used if unset.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub travel_rate: Option<f64>,
+    /// Whether to reorder segments to reduce pen-up travel; see `optimize::optimize`
+    pub optimize: bool,
+}
+
+impl Default for PlotterSettings {
+    fn default() -> Self {
+        PlotterSettings {
+            scale: None,
+            pens: BTreeMap::new(),
+            pen_up: "G0 Z5".into(),
+            pen_down: "G1 Z0".into(),
+            pen_change: "M0 (change to pen {pen})".into(),
+            feed_rate: 1000.0,
+            travel_rate: None,
+            optimize: false,
+        }
+    }
+}
+
+impl PlotterSettings {
+    pub(crate) fn validate(&self, field: &str) -> Result<(), GridError> {
+        let error = |name: &str, kind| GridError::new(format!("{}.{}", field, name), kind);
+        if let Some(scale) = self.scale {
+            positive(scale).map_err(|kind| error("scale", kind))?;
+        }
+        for (stroke, &pen) in &self.pens {
+            let name = format!("pens.{}", stroke);
+            color(stroke).map_err(|kind| error(&name, kind))?;
+            if pen == 0 {
+                return Err(error(&name, ErrorKind::NotPositive(0.0)));
+            }
+        }
+        positive(self.feed_rate).map_err(|kind| error("feed-rate", kind))?;
+        if let Some(rate) = self.travel_rate {
+            positive(rate).map_err(|kind| error("travel-rate", kind))?;
+        }
+        Ok(())
+    }
+
+    /// The pen to draw lines of color `stroke` with.
+    pub fn pen(&self, stroke: Color) -> u32 {
+        self.pens
+            .iter()
+            .find(|(color, _)| Color::parse(color) == Some(stroke))
+            .map_or(1, |(_, &pen)| pen)
+    }
+}
+
+/// Maximum distance on paper between an arc and the straight strokes drawing it, in mm.
+const ARC_TOLERANCE: f64 = 0.05;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Dialect {
+    Hpgl,
+    Gcode,
+}
+
+/// Renders grids as commands for a pen plotter, either in HPGL or G-code.
+#[derive(Debug)]
+pub struct PlotterRenderer {
+    dialect: Dialect,
+    settings: PlotterSettings,
+    bounds: Rect,
+    /// Millimeters on paper per unit of the bounds
+    scale: f64,
+    default_stroke: Color,
+    strokes: Vec<PenStrokes>,
+    /// Path statistics before and after optimization, if enabled
+    stats: Option<(PathStats, PathStats)>,
+    output: String,
+}
+
+impl PlotterRenderer {
+    pub fn hpgl(settings: PlotterSettings) -> Self {
+        PlotterRenderer::new(Dialect::Hpgl, settings)
+    }
+
+    pub fn gcode(settings: PlotterSettings) -> Self {
+        PlotterRenderer::new(Dialect::Gcode, settings)
+    }
+
+    fn new(dialect: Dialect, settings: PlotterSettings) -> Self {
+        PlotterRenderer {
+            dialect,
+            settings,
+            bounds: Rect::default(),
+            scale: 1.0,
+            default_stroke: Color::BLACK,
+            strokes: Vec::new(),
+            stats: None,
+            output: String::new(),
+        }
+    }
+
+    /// Path statistics before and after optimization, if it was enabled in the settings.
+    pub fn optimization_stats(&self) -> Option<(PathStats, PathStats)> {
+        self.stats
+    }
+
+    /// Converts a point to paper coordinates.
+    fn to_paper(&self, point: Point) -> Point {
+        Point::new(
+            (point.x - self.bounds.min_x) * self.scale,
+            (self.bounds.max_y - point.y) * self.scale,
+        )
+    }
+
+    fn write_hpgl(&mut self) {
+        // HPGL plotter units are 0.025 mm
+        let units = |p: Point| ((p.x * 40.0).round() as i64, (p.y * 40.0).round() as i64);
+        let out = &mut self.output;
+        out.push_str("IN;\n");
+        for strokes in &self.strokes {
+            let _ = writeln!(out, "SP{};", strokes.pen);
+            for segment in &strokes.segments {
+                let (x1, y1) = units(segment.start);
+                let (x2, y2) = units(segment.end);
+                let _ = writeln!(out, "PU{},{};PD{},{};", x1, y1, x2, y2);
+            }
+        }
+        out.push_str("PU;SP0;\n");
+    }
+
+    fn write_gcode(&mut self) {
+        let settings = &self.settings;
+        let out = &mut self.output;
+        let travel = |out: &mut String, p: Point| {
+            let _ = match settings.travel_rate {
+                Some(rate) => writeln!(out, "G1 X{:.3} Y{:.3} F{}", p.x, p.y, rate),
+                None => writeln!(out, "G0 X{:.3} Y{:.3}", p.x, p.y),
+            };
+        };
+        // set the feed rate up front, since the pen-down move may not give one
+        let _ = writeln!(out, "G21\nG90\nF{}", settings.feed_rate);
+        let _ = writeln!(out, "{}", settings.pen_up);
+        let mut current_pen = None;
+        for strokes in self
+            .strokes
+            .iter()
+            .filter(|strokes| !strokes.segments.is_empty())
+        {
+            if current_pen != Some(strokes.pen) {
+                let _ = writeln!(
+                    out,
+                    "{}",
+                    settings
+                        .pen_change
+                        .replace("{pen}", &strokes.pen.to_string())
+                );
+            }
+            current_pen = Some(strokes.pen);
+            for segment in &strokes.segments {
+                travel(out, segment.start);
+                let _ = writeln!(out, "{}", settings.pen_down);
+                let _ = writeln!(
+                    out,
+                    "G1 X{:.3} Y{:.3} F{}",
+                    segment.end.x, segment.end.y, settings.feed_rate
+                );
+                let _ = writeln!(out, "{}", settings.pen_up);
+            }
+        }
+        travel(out, Point::default());
+        out.push_str("M2\n");
+    }
+}
+
+impl Renderer for PlotterRenderer {
+    fn begin_document(&mut self, bounds: Rect, _area: &Region, units: Option<Unit>, style: &Style) {
+        self.bounds = bounds;
+        self.scale = self
+            .settings
+            .scale
+            .or_else(|| Some(units?.convert(1.0, Unit::Mm)))
+            .unwrap_or(1.0);
+        self.default_stroke = style.stroke.and_then(Color::parse).unwrap_or(Color::BLACK);
+        self.strokes.clear();
+    }
+
+    fn begin_group(&mut self, group: &GroupInfo) {
+        let style = &group.style;
+        let stroke = style
+            .stroke
+            .and_then(Color::parse)
+            .unwrap_or(self.default_stroke);
+        self.strokes.push(PenStrokes {
+            pen: self.settings.pen(stroke),
+            segments: Vec::new(),
+        });
+    }
+
+    fn segment(&mut self, segment: &Segment) {
+        let segment = Segment {
+            start: self.to_paper(segment.start),
+            end: self.to_paper(segment.end),
+        };
+        self.strokes
+            .last_mut()
+            .expect("segment outside of group")
+            .segments
+            .push(segment);
+    }
+
+    fn arc(&mut self, arc: &Arc) {
+        let points = arc.flatten(ARC_TOLERANCE / self.scale);
+        for pair in points.windows(2) {
+            self.segment(&Segment::new(pair[0], pair[1]));
+        }
+    }
+
+    fn polygon(&mut self, points: &[Point]) {
+        let next = points.iter().cycle().skip(1);
+        for (&p, &q) in points.iter().zip(next) {
+            self.segment(&Segment::new(p, q));
+        }
+    }
+
+    fn dot(&mut self, center: Point, radius: f64) {
+        // pens can't fill, so draw the outline
+        self.arc(&Arc::circle(center, radius));
+    }
+
+    fn fill(&mut self, _points: &[Point], _color: &str) {
+        // pens can't fill areas, so fills are left out
+    }
+
+    fn end_group(&mut self) {}
+
+    fn end_document(&mut self) {
+        if self.settings.optimize {
+            let before = PathStats::measure(&self.strokes);
+            self.strokes = optimize(std::mem::take(&mut self.strokes));
+            self.stats = Some((before, PathStats::measure(&self.strokes)));
+        }
+        self.output.clear();
+        match self.dialect {
+            Dialect::Hpgl => self.write_hpgl(),
+            Dialect::Gcode => self.write_gcode(),
+        }
+    }
+
+    fn write(&self, out: &mut dyn io::Write) -> io::Result<()> {
+        out.write_all(self.output.as_bytes())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Grid, GridCollection};
+
+    fn gcode(settings: PlotterSettings) -> String {
+        let grids = GridCollection::new(Rect::new(0.0, 10.0, 0.0, 10.0))
+            .with_grid(Grid::new(5.0).with_stroke("red"))
+            .with_grid(Grid::new(5.0).with_theta(90.0));
+        let mut renderer = PlotterRenderer::gcode(settings);
+        grids.render(&mut renderer).unwrap();
+        let mut out = Vec::new();
+        renderer.write(&mut out).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn selects_each_pen_before_drawing() {
+        let mut settings = PlotterSettings::default();
+        settings.pens.insert("red".into(), 2);
+        settings.pen_change = "T{pen}".into();
+        let out = gcode(settings);
+        let lines = out.lines().collect::<Vec<_>>();
+        let changes = lines
+            .iter()
+            .enumerate()
+            .filter(|(_, line)| line.starts_with('T'))
+            .collect::<Vec<_>>();
+        assert_eq!(changes.len(), 2);
+        assert_eq!(*changes[0].1, "T2");
+        assert_eq!(*changes[1].1, "T1");
+        let first_down = lines.iter().position(|&line| line == "G1 Z0").unwrap();
+        assert!(changes[0].0 < first_down);
+    }
+
+    #[test]
+    fn sets_a_feed_rate_before_the_pen_goes_down() {
+        let out = gcode(PlotterSettings::default());
+        let feed = out.find("F1000\n").unwrap();
+        assert!(feed < out.find("G1 Z0").unwrap());
+    }
+}
@@ -0,0 +1,217 @@
+use std::io;
+
+use tiny_skia::{FillRule, Paint, PathBuilder, Pixmap, Stroke, Transform};
+
+use super::{GroupInfo, Renderer, Style};
+use crate::{Arc, Color, Point, Rect, Region, Segment, Unit};
+
+/// Resolution of a rendered image.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Resolution {
+    /// Dots per inch, taking one unit of the bounds to be a CSS pixel (1/96 inch) unless the
+    /// collection has physical units
+    Dpi(f64),
+    /// Fit the bounds within the given size in pixels, preserving their aspect ratio. If only one
+    /// dimension is given, the other is chosen to match the bounds.
+    Size {
+        width: Option<u32>,
+        height: Option<u32>,
+    },
+}
+
+impl Default for Resolution {
+    fn default() -> Self {
+        Resolution::Dpi(96.0)
+    }
+}
+
+impl Resolution {
+    /// The number of pixels per unit of `bounds`, which are in `units`.
+    fn scale(self, bounds: Rect, units: Unit) -> f64 {
+        match self {
+            Resolution::Dpi(dpi) => units.convert(1.0, Unit::In) * dpi,
+            Resolution::Size { width, height } => {
+                let x_scale = width.map(|width| width as f64 / bounds.width());
+                let y_scale = height.map(|height| height as f64 / bounds.height());
+                match (x_scale, y_scale) {
+                    (Some(x), Some(y)) => x.min(y),
+                    (Some(scale), None) | (None, Some(scale)) => scale,
+                    (None, None) => 1.0,
+                }
+            }
+        }
+    }
+}
+
+/// Renders grids as an anti-aliased PNG image.
+pub struct PngRenderer {
+    resolution: Resolution,
+    background: Color,
+    /// `None` if the image is too large to allocate
+    pixmap: Option<Pixmap>,
+    transform: Transform,
+    document_style: Style<'static>,
+    default_stroke: Color,
+    stroke: Color,
+    stroke_width: f64,
+    path: PathBuilder,
+}
+
+impl PngRenderer {
+    pub fn new(resolution: Resolution) -> Self {
+        PngRenderer {
+            resolution,
+            background: Color::TRANSPARENT,
+            pixmap: None,
+            transform: Transform::identity(),
+            document_style: Style::default(),
+            default_stroke: Color::BLACK,
+            stroke: Color::BLACK,
+            stroke_width: 1.0,
+            path: PathBuilder::new(),
+        }
+    }
+
+    /// Sets the background color. Defaults to transparent.
+    pub fn with_background(mut self, background: Color) -> Self {
+        self.background = background;
+        self
+    }
+}
+
+impl Renderer for PngRenderer {
+    fn begin_document(&mut self, bounds: Rect, _area: &Region, units: Option<Unit>, style: &Style) {
+        let scale = self.resolution.scale(bounds, units.unwrap_or_default());
+        let width = (bounds.width() * scale).ceil().max(1.0) as u32;
+        let height = (bounds.height() * scale).ceil().max(1.0) as u32;
+        self.pixmap = Pixmap::new(width, height);
+        if let Some(pixmap) = &mut self.pixmap {
+            pixmap.fill(skia_color(self.background));
+        }
+        self.transform = Transform::from_row(
+            scale as f32,
+            0.0,
+            0.0,
+            scale as f32,
+            (-bounds.min_x * scale) as f32,
+            (-bounds.min_y * scale) as f32,
+        );
+        self.document_style = Style {
+            stroke: None,
+            stroke_width: style.stroke_width,
+        };
+        self.default_stroke = style.stroke.and_then(Color::parse).unwrap_or(Color::BLACK);
+    }
+
+    fn begin_group(&mut self, group: &GroupInfo) {
+        let style = &group.style;
+        self.stroke = style
+            .stroke
+            .and_then(Color::parse)
+            .unwrap_or(self.default_stroke);
+        self.stroke_width = style
+            .stroke_width
+            .or(self.document_style.stroke_width)
+            .unwrap_or(1.0);
+        self.path = PathBuilder::new();
+    }
+
+    fn segment(&mut self, segment: &Segment) {
+        self.path
+            .move_to(segment.start.x as f32, segment.start.y as f32);
+        self.path
+            .line_to(segment.end.x as f32, segment.end.y as f32);
+    }
+
+    fn arc(&mut self, arc: &Arc) {
+        let start = arc.start_point();
+        self.path.move_to(start.x as f32, start.y as f32);
+        for [_, c1, c2, end] in arc.beziers() {
+            self.path.cubic_to(
+                c1.x as f32,
+                c1.y as f32,
+                c2.x as f32,
+                c2.y as f32,
+                end.x as f32,
+                end.y as f32,
+            );
+        }
+    }
+
+    fn polygon(&mut self, points: &[Point]) {
+        if let [first, rest @ ..] = points {
+            self.path.move_to(first.x as f32, first.y as f32);
+            for p in rest {
+                self.path.line_to(p.x as f32, p.y as f32);
+            }
+            self.path.close();
+        }
+    }
+
+    fn dot(&mut self, center: Point, radius: f64) {
+        let (pixmap, path) = match (
+            &mut self.pixmap,
+            PathBuilder::from_circle(center.x as f32, center.y as f32, radius as f32),
+        ) {
+            (Some(pixmap), Some(path)) => (pixmap, path),
+            _ => return,
+        };
+        let mut paint = Paint::default();
+        paint.set_color(skia_color(self.stroke));
+        paint.anti_alias = true;
+        pixmap.fill_path(&path, &paint, FillRule::Winding, self.transform, None);
+    }
+
+    fn fill(&mut self, points: &[Point], color: &str) {
+        let mut path = PathBuilder::new();
+        if let [first, rest @ ..] = points {
+            path.move_to(first.x as f32, first.y as f32);
+            for p in rest {
+                path.line_to(p.x as f32, p.y as f32);
+            }
+            path.close();
+        }
+        let (pixmap, path) = match (&mut self.pixmap, path.finish()) {
+            (Some(pixmap), Some(path)) => (pixmap, path),
+            _ => return,
+        };
+        let mut paint = Paint::default();
+        paint.set_color(skia_color(Color::parse(color).unwrap_or(Color::BLACK)));
+        paint.anti_alias = true;
+        pixmap.fill_path(&path, &paint, FillRule::Winding, self.transform, None);
+    }
+
+    fn end_group(&mut self) {
+        let path = std::mem::take(&mut self.path);
+        let (pixmap, path) = match (&mut self.pixmap, path.finish()) {
+            (Some(pixmap), Some(path)) => (pixmap, path),
+            _ => return,
+        };
+        if self.stroke_width == 0.0 {
+            return;
+        }
+        let mut paint = Paint::default();
+        paint.set_color(skia_color(self.stroke));
+        paint.anti_alias = true;
+        let stroke = Stroke {
+            width: self.stroke_width as f32,
+            ..Default::default()
+        };
+        pixmap.stroke_path(&path, &paint, &stroke, self.transform, None);
+    }
+
+    fn end_document(&mut self) {}
+
+    fn write(&self, out: &mut dyn io::Write) -> io::Result<()> {
+        let pixmap = self
+            .pixmap
+            .as_ref()
+            .ok_or_else(|| io::Error::other("image is too large"))?;
+        let png = pixmap.encode_png().map_err(io::Error::other)?;
+        out.write_all(&png)
+    }
+}
+
+fn skia_color(color: Color) -> tiny_skia::Color {
+    tiny_skia::Color::from_rgba8(color.r, color.g, color.b, color.a)
+}
@@ -0,0 +1,270 @@
+use std::{io, mem};
+
+use svg::{
+    node::element::{
+        path::Data, Circle, ClipPath, Definitions, Ellipse, Group, Line, Path, Polygon, Rectangle,
+    },
+    Document, Node,
+};
+
+use super::{GroupInfo, Renderer, Style};
+use crate::{Arc, Point, Rect, Region, Segment, Shape, Unit};
+
+/// Renders grids as an SVG document.
+#[derive(Debug)]
+pub struct SvgRenderer {
+    clip_path: bool,
+    document: Document,
+    main_group: Group,
+    group: Group,
+    /// Default stroke color of the document
+    stroke: String,
+    /// Color of dots in the current group
+    fill: String,
+}
+
+impl SvgRenderer {
+    pub fn new() -> Self {
+        SvgRenderer {
+            clip_path: false,
+            document: Document::new(),
+            main_group: Group::new(),
+            group: Group::new(),
+            stroke: String::new(),
+            fill: String::new(),
+        }
+    }
+
+    /// Sets whether to emit an SVG clip-path for the viewable area, in addition to clipping the
+    /// lines themselves.
+    pub fn with_clip_path(mut self, clip_path: bool) -> Self {
+        self.clip_path = clip_path;
+        self
+    }
+
+    pub fn into_document(self) -> Document {
+        self.document
+    }
+}
+
+impl Renderer for SvgRenderer {
+    fn begin_document(&mut self, bounds: Rect, area: &Region, units: Option<Unit>, style: &Style) {
+        self.document = Document::new().set(
+            "viewBox",
+            (bounds.min_x, bounds.min_y, bounds.width(), bounds.height()),
+        );
+        if let Some(units) = units {
+            self.document
+                .assign("width", format!("{}{}", bounds.width(), units));
+            self.document
+                .assign("height", format!("{}{}", bounds.height(), units));
+        }
+        if self.clip_path {
+            let clip_path = ClipPath::new().set("id", "viewable-area");
+            self.document
+                .append(Definitions::new().add(add_clip_shape(clip_path, area)));
+        }
+        self.main_group = Group::new();
+        assign_style(&mut self.main_group, style);
+        self.stroke = style.stroke.unwrap_or("black").into();
+    }
+
+    fn begin_group(&mut self, group: &GroupInfo) {
+        let style = &group.style;
+        self.group = Group::new();
+        if self.clip_path {
+            self.group.assign("clip-path", "url(#viewable-area)");
+        }
+        assign_style(&mut self.group, style);
+        self.fill = style.stroke.unwrap_or(&self.stroke).into();
+    }
+
+    fn segment(&mut self, segment: &Segment) {
+        self.group.append(
+            Line::new()
+                .set("x1", segment.start.x)
+                .set("x2", segment.end.x)
+                .set("y1", segment.start.y)
+                .set("y2", segment.end.y),
+        );
+    }
+
+    fn arc(&mut self, arc: &Arc) {
+        if arc.is_circle() {
+            self.group.append(
+                Circle::new()
+                    .set("cx", arc.center.x)
+                    .set("cy", arc.center.y)
+                    .set("r", arc.radius)
+                    .set("fill", "none"),
+            );
+            return;
+        }
+        let (start, end) = (arc.start_point(), arc.end_point());
+        let large_arc = if arc.sweep > 180.0 { 1 } else { 0 };
+        let data = Data::new()
+            .move_to((start.x, start.y))
+            .elliptical_arc_to((arc.radius, arc.radius, 0, large_arc, 1, end.x, end.y));
+        self.group
+            .append(Path::new().set("d", data).set("fill", "none"));
+    }
+
+    fn polygon(&mut self, points: &[Point]) {
+        self.group.append(
+            Polygon::new()
+                .set("points", svg_points(points))
+                .set("fill", "none"),
+        );
+    }
+
+    fn dot(&mut self, center: Point, radius: f64) {
+        self.group.append(
+            Circle::new()
+                .set("cx", center.x)
+                .set("cy", center.y)
+                .set("r", radius)
+                .set("fill", &*self.fill)
+                .set("stroke", "none"),
+        );
+    }
+
+    fn fill(&mut self, points: &[Point], color: &str) {
+        self.group.append(
+            Polygon::new()
+                .set("points", svg_points(points))
+                .set("fill", color)
+                .set("stroke", "none"),
+        );
+    }
+
+    fn end_group(&mut self) {
+        self.main_group.append(mem::take(&mut self.group));
+    }
+
+    fn end_document(&mut self) {
+        self.document.append(mem::take(&mut self.main_group));
+    }
+
+    fn write(&self, out: &mut dyn io::Write) -> io::Result<()> {
+        svg::write(out, &self.document)
+    }
+}
+
+impl Default for SvgRenderer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Formats points for the `points` attribute of a polygon.
+fn svg_points(points: &[Point]) -> String {
+    points
+        .iter()
+        .map(|p| format!("{},{}", p.x, p.y))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Adds the outline of `area` to `clip_path`. Regions with holes become a single path using the
+/// even-odd rule, so holes must not overlap each other.
+fn add_clip_shape(clip_path: ClipPath, area: &Region) -> ClipPath {
+    if !area.holes.is_empty() {
+        let data = area
+            .holes
+            .iter()
+            .fold(shape_data(Data::new(), &area.shape), shape_data);
+        return clip_path.add(Path::new().set("d", data).set("clip-rule", "evenodd"));
+    }
+    match &area.shape {
+        Shape::Rect(rect) => {
+            let rect = rect.get();
+            clip_path.add(
+                Rectangle::new()
+                    .set("x", rect.min_x)
+                    .set("y", rect.min_y)
+                    .set("width", rect.width())
+                    .set("height", rect.height()),
+            )
+        }
+        Shape::Polygon(points) => {
+            let points = points.iter().map(|p| p.get()).collect::<Vec<_>>();
+            clip_path.add(Polygon::new().set("points", svg_points(&points)))
+        }
+        Shape::Circle(circle) => clip_path.add(
+            Circle::new()
+                .set("cx", circle.cx.get())
+                .set("cy", circle.cy.get())
+                .set("r", circle.radius.get()),
+        ),
+        Shape::Ellipse(ellipse) => {
+            let (cx, cy) = (ellipse.cx.get(), ellipse.cy.get());
+            let mut element = Ellipse::new()
+                .set("cx", cx)
+                .set("cy", cy)
+                .set("rx", ellipse.rx.get())
+                .set("ry", ellipse.ry.get());
+            if ellipse.theta != 0.0 {
+                element.assign(
+                    "transform",
+                    format!("rotate({} {} {})", ellipse.theta, cx, cy),
+                );
+            }
+            clip_path.add(element)
+        }
+    }
+}
+
+/// Appends the outline of `shape` to `data` as a closed subpath.
+fn shape_data(data: Data, shape: &Shape) -> Data {
+    let polygon = |data: Data, points: &[Point]| {
+        let data = data.move_to((points[0].x, points[0].y));
+        points[1..]
+            .iter()
+            .fold(data, |data, p| data.line_to((p.x, p.y)))
+            .close()
+    };
+    match shape {
+        Shape::Rect(rect) => {
+            let rect = rect.get();
+            polygon(
+                data,
+                &[
+                    Point::new(rect.min_x, rect.min_y),
+                    Point::new(rect.max_x, rect.min_y),
+                    Point::new(rect.max_x, rect.max_y),
+                    Point::new(rect.min_x, rect.max_y),
+                ],
+            )
+        }
+        Shape::Polygon(points) => {
+            polygon(data, &points.iter().map(|p| p.get()).collect::<Vec<_>>())
+        }
+        Shape::Circle(circle) => {
+            let (center, r) = (circle.center(), circle.radius.get());
+            data.move_to((center.x + r, center.y))
+                .elliptical_arc_to((r, r, 0, 1, 1, center.x - r, center.y))
+                .elliptical_arc_to((r, r, 0, 1, 1, center.x + r, center.y))
+                .close()
+        }
+        Shape::Ellipse(ellipse) => {
+            // ends of the axis along `rx`, rotated into place
+            let angle = ellipse.theta.to_radians();
+            let (center, rx, ry) = (ellipse.center(), ellipse.rx.get(), ellipse.ry.get());
+            let (dx, dy) = (rx * angle.cos(), rx * angle.sin());
+            let theta = ellipse.theta;
+            data.move_to((center.x + dx, center.y + dy))
+                .elliptical_arc_to((rx, ry, theta, 1, 1, center.x - dx, center.y - dy))
+                .elliptical_arc_to((rx, ry, theta, 1, 1, center.x + dx, center.y + dy))
+                .close()
+        }
+    }
+}
+
+fn assign_style(group: &mut Group, style: &Style) {
+    if let Some(stroke) = style.stroke {
+        group.assign("stroke", stroke);
+    }
+    if let Some(width) = style.stroke_width {
+        group.assign("stroke-width", width);
+    }
+}
@@ -0,0 +1,347 @@
+use std::collections::HashSet;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    error::{color, finite, non_negative, ErrorKind, GridError},
+    units::{Conversion, Length, ResolveUnits},
+    Point, Rect, Segment,
+};
+
+/// Any periodic tiling, described by the edges of one cell and the two vectors along which the
+/// cell repeats. Points may be written as `[x, y]`, and edges as `[[x1, y1], [x2, y2]]`.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(default, rename_all = "kebab-case", deny_unknown_fields)]
+pub struct PeriodicGrid {
+    /// Name of the grid, used for layer names in formats which have them
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    /// x-coordinate of the origin of the cell
+    pub cx: Length,
+    /// y-coordinate of the origin of the cell
+    pub cy: Length,
+    /// Rotation clockwise about the origin of the cell, in degrees
+    pub theta: f64,
+    /// First lattice vector
+    pub a: Point<Length>,
+    /// Second lattice vector, which must not be parallel to the first
+    pub b: Point<Length>,
+    /// Edges of the cell, relative to its origin
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub edges: Vec<Segment<Length>>,
+    /// Closed polygons whose sides are added to the edges of the cell
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub polygons: Vec<Vec<Point<Length>>>,
+    /// Stroke color
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stroke: Option<String>,
+    /// Stroke width
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stroke_width: Option<Length>,
+}
+
+impl Default for PeriodicGrid {
+    fn default() -> Self {
+        PeriodicGrid::new(Point::new(1.0, 0.0), Point::new(0.0, 1.0))
+    }
+}
+
+impl PeriodicGrid {
+    /// Creates a tiling repeating along the lattice vectors `a` and `b`, with an empty cell.
+    pub fn new(a: impl Into<Point>, b: impl Into<Point>) -> Self {
+        PeriodicGrid {
+            name: None,
+            cx: Length::new(0.0),
+            cy: Length::new(0.0),
+            theta: 0.0,
+            a: a.into().into(),
+            b: b.into().into(),
+            edges: Vec::new(),
+            polygons: Vec::new(),
+            stroke: None,
+            stroke_width: None,
+        }
+    }
+
+    pub fn with_name(mut self, name: impl Into<String>) -> Self {
+        self.name = Some(name.into());
+        self
+    }
+
+    /// Sets the origin of the cell.
+    pub fn with_center(mut self, cx: f64, cy: f64) -> Self {
+        self.cx = cx.into();
+        self.cy = cy.into();
+        self
+    }
+
+    /// Sets the rotation of the tiling, in degrees clockwise.
+    pub fn with_theta(mut self, theta: f64) -> Self {
+        self.theta = theta;
+        self
+    }
+
+    /// Adds an edge to the cell.
+    pub fn with_edge(mut self, edge: Segment) -> Self {
+        self.edges.push(edge.into());
+        self
+    }
+
+    /// Adds the sides of a closed polygon to the cell.
+    pub fn with_polygon(mut self, polygon: impl IntoIterator<Item = Point>) -> Self {
+        self.polygons
+            .push(polygon.into_iter().map(Point::into).collect());
+        self
+    }
+
+    pub fn with_stroke(mut self, stroke: impl Into<String>) -> Self {
+        self.stroke = Some(stroke.into());
+        self
+    }
+
+    pub fn with_stroke_width(mut self, stroke_width: f64) -> Self {
+        self.stroke_width = Some(stroke_width.into());
+        self
+    }
+
+    /// Returns the edges of the tiling which cross `area`, clipped to it, with edges shared by
+    /// neighboring cells appearing only once.
+    ///
+    /// The grid must be valid; see `GridCollection::validate`.
+    pub fn segments(&self, area: Rect) -> Vec<Segment> {
+        let sides = self.polygons.iter().flat_map(|polygon| {
+            let next = polygon.iter().cycle().skip(1);
+            polygon
+                .iter()
+                .zip(next)
+                .map(|(p, q)| Segment::new(p.get(), q.get()))
+        });
+        PeriodicEdges {
+            edges: self
+                .edges
+                .iter()
+                .map(|edge| edge.get())
+                .chain(sides)
+                .collect(),
+            a: self.a.get(),
+            b: self.b.get(),
+        }
+        .transform(Point::new(self.cx.get(), self.cy.get()), self.theta)
+        .segments(area)
+    }
+
+    pub(crate) fn validate(&self) -> Result<(), GridError> {
+        for (name, value) in [
+            ("cx", self.cx.get()),
+            ("cy", self.cy.get()),
+            ("theta", self.theta),
+        ] {
+            finite(value).map_err(|kind| GridError::new(name, kind))?;
+        }
+        let point = |field: String, p: Point<Length>| {
+            let p = p.get();
+            finite(p.x).map_err(|kind| GridError::new(format!("{}.x", field), kind))?;
+            finite(p.y).map_err(|kind| GridError::new(format!("{}.y", field), kind))?;
+            Ok::<_, GridError>(())
+        };
+        point("a".into(), self.a)?;
+        point("b".into(), self.b)?;
+        // the area of the cell, relative to the lengths of its sides, or NaN if one is zero
+        let (a, b) = (self.a.get(), self.b.get());
+        let sine = a.cross(b) / (a.length() * b.length());
+        if sine.is_nan() || sine.abs() <= 1e-9 {
+            return Err(GridError::new("b", ErrorKind::Parallel));
+        }
+        for (i, edge) in self.edges.iter().enumerate() {
+            point(format!("edges.{}.0", i), edge.start)?;
+            point(format!("edges.{}.1", i), edge.end)?;
+        }
+        for (i, polygon) in self.polygons.iter().enumerate() {
+            for (j, &p) in polygon.iter().enumerate() {
+                point(format!("polygons.{}.{}", i, j), p)?;
+            }
+        }
+        if let Some(stroke) = &self.stroke {
+            color(stroke).map_err(|kind| GridError::new("stroke", kind))?;
+        }
+        if let Some(width) = self.stroke_width {
+            non_negative(width.get()).map_err(|kind| GridError::new("stroke-width", kind))?;
+        }
+        Ok(())
+    }
+}
+
+impl ResolveUnits for PeriodicGrid {
+    fn resolve_units(&mut self, units: &mut Conversion) {
+        self.cx.resolve_units(units);
+        self.cy.resolve_units(units);
+        self.a.resolve_units(units);
+        self.b.resolve_units(units);
+        self.edges.resolve_units(units);
+        self.polygons.resolve_units(units);
+        self.stroke_width.resolve_units(units);
+    }
+}
+
+/// Maximum distance between the ends of two edges for them to be considered the same edge.
+const TOLERANCE: f64 = 1e-6;
+
+/// A tiling which repeats the edges of one cell along two lattice vectors.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct PeriodicEdges {
+    /// Edges of the cell at the origin. Edges shared with neighboring cells may be included; they
+    /// are only drawn once.
+    pub edges: Vec<Segment>,
+    pub a: Point,
+    pub b: Point,
+}
+
+impl PeriodicEdges {
+    /// Rotates the tiling by `theta` degrees clockwise about the origin, and then moves the origin
+    /// to `center`.
+    pub fn transform(self, center: Point, theta: f64) -> Self {
+        let (sin, cos) = theta.to_radians().sin_cos();
+        let rotate = |p: Point| Point::new(p.x * cos - p.y * sin, p.x * sin + p.y * cos);
+        PeriodicEdges {
+            edges: self
+                .edges
+                .iter()
+                .map(|edge| Segment::new(rotate(edge.start) + center, rotate(edge.end) + center))
+                .collect(),
+            a: rotate(self.a),
+            b: rotate(self.b),
+        }
+    }
+
+    /// Returns the edges of all cells which cross `area`, clipped to it, with each edge appearing
+    /// only once. The lattice vectors must not be parallel.
+    pub fn segments(&self, area: Rect) -> Vec<Segment> {
+        let origin = self
+            .edges
+            .first()
+            .map_or(Point::default(), |edge| edge.start);
+        // distance from `origin` within which every edge of the cell lies
+        let reach = self
+            .edges
+            .iter()
+            .flat_map(|edge| [edge.start, edge.end])
+            .map(|p| p.distance(origin))
+            .fold(0.0, f64::max);
+        // lattice coordinates of the corners of the area, grown by `reach`
+        let det = self.a.cross(self.b);
+        let to_lattice = |p: Point| {
+            let p = p - origin;
+            (p.cross(self.b) / det, self.a.cross(p) / det)
+        };
+        let corners = [
+            Point::new(area.min_x - reach, area.min_y - reach),
+            Point::new(area.max_x + reach, area.min_y - reach),
+            Point::new(area.min_x - reach, area.max_y + reach),
+            Point::new(area.max_x + reach, area.max_y + reach),
+        ]
+        .map(to_lattice);
+        let range = |coord: fn(&(f64, f64)) -> f64| {
+            let min = corners.iter().map(coord).fold(f64::INFINITY, f64::min);
+            let max = corners.iter().map(coord).fold(f64::NEG_INFINITY, f64::max);
+            (min.floor() as i64)..=(max.ceil() as i64)
+        };
+
+        let mut seen = HashSet::new();
+        let mut segments = Vec::new();
+        for j in range(|c| c.1) {
+            for i in range(|c| c.0) {
+                let shift = self.a * i as f64 + self.b * j as f64;
+                for edge in &self.edges {
+                    let edge = Segment::new(edge.start + shift, edge.end + shift);
+                    if !seen.insert(edge_key(edge)) {
+                        continue;
+                    }
+                    if let Some(segment) = area.clip_segment(edge) {
+                        segments.push(segment);
+                    }
+                }
+            }
+        }
+        segments
+    }
+}
+
+/// Identifies an edge regardless of its direction, to within `TOLERANCE`.
+fn edge_key(edge: Segment) -> [(i64, i64); 2] {
+    let quantize = |p: Point| {
+        (
+            (p.x / TOLERANCE).round() as i64,
+            (p.y / TOLERANCE).round() as i64,
+        )
+    };
+    let (start, end) = (quantize(edge.start), quantize(edge.end));
+    if start <= end {
+        [start, end]
+    } else {
+        [end, start]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn identifies_edges_either_way_round() {
+        let edge = Segment::new((1.0, 2.0), (3.0, 4.0));
+        assert_eq!(edge_key(edge), edge_key(Segment::new(edge.end, edge.start)));
+        let nudged = Segment::new((1.0 + 1e-9, 2.0), (3.0, 4.0 - 1e-9));
+        assert_eq!(edge_key(edge), edge_key(nudged));
+        let other = Segment::new((1.0, 2.0), (3.0, 4.1));
+        assert_ne!(edge_key(edge), edge_key(other));
+    }
+
+    #[test]
+    fn shared_edges_appear_once() {
+        // every side of each square is shared with a neighbor
+        let grid = PeriodicGrid::new((10.0, 0.0), (0.0, 10.0)).with_polygon([
+            Point::new(0.0, 0.0),
+            Point::new(10.0, 0.0),
+            Point::new(10.0, 10.0),
+            Point::new(0.0, 10.0),
+        ]);
+        let segments = grid.segments(Rect::new(1.0, 29.0, 1.0, 29.0));
+        // three pieces along each of the lines at 10 and 20 in either direction
+        assert_eq!(segments.len(), 12);
+        let keys = segments.iter().map(|&segment| edge_key(segment));
+        assert_eq!(keys.collect::<HashSet<_>>().len(), segments.len());
+        let length = segments.iter().map(|segment| segment.length()).sum::<f64>();
+        assert!((length - 4.0 * 28.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn shared_edges_appear_once_despite_rounding() {
+        let size = 7.0;
+        let hexagon = (0..6).map(|k| {
+            let angle = (k as f64 * 60.0).to_radians();
+            Point::new(angle.cos(), angle.sin()) * size
+        });
+        let spacing = 3f64.sqrt() * size;
+        let neighbor = |angle: f64| {
+            let angle = angle.to_radians();
+            Point::new(angle.cos(), angle.sin()) * spacing
+        };
+        let grid = PeriodicGrid::new(neighbor(30.0), neighbor(90.0))
+            .with_polygon(hexagon)
+            .with_center(3.3, 1.7)
+            .with_theta(17.0);
+        let segments = grid.segments(Rect::new(0.0, 100.0, 0.0, 100.0));
+        let same = |a: &Segment, b: &Segment| {
+            let close = |p: Point, q: Point| p.distance(q) < 1e-6;
+            (close(a.start, b.start) && close(a.end, b.end))
+                || (close(a.start, b.end) && close(a.end, b.start))
+        };
+        for (i, a) in segments.iter().enumerate() {
+            assert!(segments[i + 1..].iter().all(|b| !same(a, b)), "{:?}", a);
+        }
+        // each hexagon has three edges of its own
+        let hexagons = 100.0 * 100.0 / (spacing * spacing * 3f64.sqrt() / 2.0);
+        let length = segments.iter().map(|segment| segment.length()).sum::<f64>();
+        assert!((length / (3.0 * size) / hexagons - 1.0).abs() < 0.1);
+    }
+}
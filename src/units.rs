@@ -0,0 +1,227 @@
+use std::{fmt, str::FromStr};
+
+use serde::{
+    de::{self, Visitor},
+    Deserialize, Deserializer, Serialize, Serializer,
+};
+
+/// A physical unit of length.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Unit {
+    Mm,
+    Cm,
+    In,
+    /// PostScript points, 1/72 inch
+    Pt,
+    /// CSS pixels, 1/96 inch
+    #[default]
+    Px,
+}
+
+impl Unit {
+    /// The number of this unit in an inch.
+    pub fn per_inch(self) -> f64 {
+        match self {
+            Unit::Mm => 25.4,
+            Unit::Cm => 2.54,
+            Unit::In => 1.0,
+            Unit::Pt => 72.0,
+            Unit::Px => 96.0,
+        }
+    }
+
+    /// Converts `value` from this unit to `unit`.
+    pub fn convert(self, value: f64, unit: Unit) -> f64 {
+        value * unit.per_inch() / self.per_inch()
+    }
+
+    /// The suffix of lengths in this unit, which is also its CSS name.
+    pub fn suffix(self) -> &'static str {
+        match self {
+            Unit::Mm => "mm",
+            Unit::Cm => "cm",
+            Unit::In => "in",
+            Unit::Pt => "pt",
+            Unit::Px => "px",
+        }
+    }
+
+    pub fn from_suffix(suffix: &str) -> Option<Self> {
+        [Unit::Mm, Unit::Cm, Unit::In, Unit::Pt, Unit::Px]
+            .into_iter()
+            .find(|unit| unit.suffix() == suffix)
+    }
+}
+
+impl fmt::Display for Unit {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.suffix())
+    }
+}
+
+/// A length, either in the units of the collection or in a unit of its own. In YAML, it is given
+/// as a plain number in the units of the collection, or as a string with a unit suffix such as
+/// `5mm` or `0.25in`.
+///
+/// Lengths keep their unit when they are deserialized, and are converted to the units of the
+/// collection once it has been read; see `GridCollection::resolve_units`.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Length {
+    pub value: f64,
+    /// Unit of `value`, or `None` if it is in the units of the collection
+    pub unit: Option<Unit>,
+}
+
+impl Length {
+    /// A length in the units of the collection.
+    pub const fn new(value: f64) -> Self {
+        Length { value, unit: None }
+    }
+
+    /// A length in `unit`, whatever the units of the collection.
+    pub const fn with_unit(value: f64, unit: Unit) -> Self {
+        Length {
+            value,
+            unit: Some(unit),
+        }
+    }
+
+    /// The length in the units of the collection. A length whose unit hasn't been resolved yet is
+    /// converted to px, the units of a collection which doesn't set any.
+    pub fn get(self) -> f64 {
+        self.to(Unit::default())
+    }
+
+    /// The length in `units`, taking a length without a unit to be in `units` already.
+    pub fn to(self, units: Unit) -> f64 {
+        match self.unit {
+            Some(unit) => unit.convert(self.value, units),
+            None => self.value,
+        }
+    }
+}
+
+impl From<f64> for Length {
+    fn from(value: f64) -> Self {
+        Length::new(value)
+    }
+}
+
+impl FromStr for Length {
+    type Err = ParseLengthError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let split = s.find(|c: char| c.is_ascii_alphabetic()).unwrap_or(s.len());
+        let value = s[..split]
+            .trim_end()
+            .parse::<f64>()
+            .map_err(|_| ParseLengthError)?;
+        match &s[split..] {
+            "" => Ok(Length::new(value)),
+            suffix => Unit::from_suffix(suffix)
+                .map(|unit| Length::with_unit(value, unit))
+                .ok_or(ParseLengthError),
+        }
+    }
+}
+
+impl fmt::Display for Length {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.unit {
+            Some(unit) => write!(f, "{}{}", self.value, unit),
+            None => write!(f, "{}", self.value),
+        }
+    }
+}
+
+/// The error returned when a string isn't a number with an optional unit suffix.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ParseLengthError;
+
+impl fmt::Display for ParseLengthError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("expected a number, or a length such as \"5mm\"")
+    }
+}
+
+impl std::error::Error for ParseLengthError {}
+
+impl Serialize for Length {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self.unit {
+            Some(_) => serializer.collect_str(self),
+            None => serializer.serialize_f64(self.value),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Length {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_any(LengthVisitor)
+    }
+}
+
+struct LengthVisitor;
+
+impl Visitor<'_> for LengthVisitor {
+    type Value = Length;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a number, or a length such as \"5mm\"")
+    }
+
+    fn visit_i64<E: de::Error>(self, value: i64) -> Result<Length, E> {
+        Ok(Length::new(value as f64))
+    }
+
+    fn visit_u64<E: de::Error>(self, value: u64) -> Result<Length, E> {
+        Ok(Length::new(value as f64))
+    }
+
+    fn visit_f64<E: de::Error>(self, value: f64) -> Result<Length, E> {
+        Ok(Length::new(value))
+    }
+
+    fn visit_str<E: de::Error>(self, value: &str) -> Result<Length, E> {
+        value
+            .parse()
+            .map_err(|_| E::invalid_value(de::Unexpected::Str(value), &self))
+    }
+}
+
+/// Converts the lengths within a value which have a unit of their own to `units.unit`, the units
+/// of the collection.
+pub(crate) trait ResolveUnits {
+    fn resolve_units(&mut self, units: &mut Conversion);
+}
+
+/// The units lengths are being converted to, and whether any of them had a unit of its own.
+pub(crate) struct Conversion {
+    pub unit: Unit,
+    pub found_unit: bool,
+}
+
+impl ResolveUnits for Length {
+    fn resolve_units(&mut self, units: &mut Conversion) {
+        units.found_unit |= self.unit.is_some();
+        *self = Length::new(self.to(units.unit));
+    }
+}
+
+impl<T: ResolveUnits> ResolveUnits for Option<T> {
+    fn resolve_units(&mut self, units: &mut Conversion) {
+        if let Some(value) = self {
+            value.resolve_units(units);
+        }
+    }
+}
+
+impl<T: ResolveUnits> ResolveUnits for Vec<T> {
+    fn resolve_units(&mut self, units: &mut Conversion) {
+        for value in self {
+            value.resolve_units(units);
+        }
+    }
+}